@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use crate::card::Card;
+use crate::cst::Document;
+
+/// Hands out the lowest codes of a range that no loaded card uses yet.
+pub struct CodeAllocator {
+    pub range: RangeInclusive<u32>,
+    used: HashSet<u32>,
+    /// None once the range ends at `u32::MAX` and it is used up.
+    next: Option<u32>
+}
+
+/// Parses `start-end` into an inclusive code range.
+pub fn parse_range(value: &str) -> Result<RangeInclusive<u32>, String> {
+    let (start, end) = value.split_once("-").ok_or(format!("Range {} should look like start-end.", value))?;
+    let start: u32 = start.trim().parse().map_err(|e| format!("Bad range start {}: {}", start, e))?;
+    let end: u32 = end.trim().parse().map_err(|e| format!("Bad range end {}: {}", end, e))?;
+    if start > end { return Err(format!("Range {} is empty.", value)) }
+    Ok(start..=end)
+}
+
+impl CodeAllocator {
+    pub fn new(range: RangeInclusive<u32>, used: impl Iterator<Item = u32>) -> CodeAllocator {
+        let next = Some(*range.start());
+        CodeAllocator { range, used: used.collect(), next }
+    }
+
+    pub fn allocate(&mut self) -> Option<u32> {
+        while let Some(code) = self.next.filter(|c| self.range.contains(c)) {
+            self.next = code.checked_add(1);
+            if self.used.insert(code) { return Some(code) }
+        }
+        None
+    }
+
+    /// Gives every card without a code a new one, in document order. Returns indexes of the changed cards.
+    pub fn assign(&mut self, cards: &mut [Card]) -> Vec<usize> {
+        let mut assigned = Vec::new();
+        for (i, card) in cards.iter_mut().enumerate().filter(|(_, c)| c.code == 0) {
+            match self.allocate() {
+                Some(code) => {
+                    card.code = code;
+                    for pack in card.pack.iter_mut().chain(card.reprints.iter_mut()) { pack.id = code }
+                    assigned.push(i);
+                }
+                None => {
+                    eprintln!("Code range {:?} is exhausted, {} is left without a code.", self.range, card.name);
+                    break;
+                }
+            }
+        }
+        assigned
+    }
+}
+
+/// Writes `(code)` after the name on the header line of each assigned card, keeping the rest of the source.
+///
+/// `cards` are those parsed from `source`. Original XYZ headers get the code after the parenthesized Chinese name.
+pub fn write_codes(source: &str, cards: &[Card], assigned: &[usize]) -> String {
+    let mut document = Document::parse(source);
+    for (i, node) in document.cards_mut().enumerate().filter(|(i, _)| assigned.contains(i)) {
+        node.set_code(cards[i].code);
+    }
+    document.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::allocator::*;
+    use crate::card::CardTransformer;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_allocate() {
+        let source = "S.A.R.A. 无 3星 龙/调整 0 1800 (Custom)\n①：抽1张卡。\n\n原质阿尔法(100) 暗 3星 恶魔/通常 1000 1000\n起点之果。\n\n青眼白龙 通常魔法\n传说之龙。";
+        let mut cards = Xyyz::from_string(source);
+        let mut allocator = CodeAllocator::new(parse_range("100-199").unwrap(), cards.iter().map(|c| c.code));
+        let assigned = allocator.assign(&mut cards);
+        assert_eq!(assigned, vec![0, 2]);
+        let written = write_codes(source, &cards, &assigned);
+        assert_eq!(written, "S.A.R.A.(101) 无 3星 龙/调整 0 1800 (Custom)\n①：抽1张卡。\n\n原质阿尔法(100) 暗 3星 恶魔/通常 1000 1000\n起点之果。\n\n青眼白龙(102) 通常魔法\n传说之龙。");
+        assert_eq!(Xyyz::from_string(&written).iter().map(|c| c.code).collect::<Vec<_>>(), vec![101, 100, 102]);
+
+        let source = "INFO-JP000(QCSER)誇りと魂の龍(骄傲与灵魂之龙) 暗 8星 龙/特殊召唤 2500 2500";
+        let mut cards = Xyyz::from_string(source);
+        let mut allocator = CodeAllocator::new(u32::MAX - 1..=u32::MAX, std::iter::once(u32::MAX - 1));
+        let assigned = allocator.assign(&mut cards);
+        assert_eq!(write_codes(source, &cards, &assigned), "INFO-JP000(QCSER)誇りと魂の龍(骄傲与灵魂之龙)(4294967295) 暗 8星 龙/特殊召唤 2500 2500");
+        assert_eq!(allocator.allocate(), None);
+    }
+}
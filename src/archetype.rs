@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+
+use crate::card::Card;
+use crate::cst::{Document, LineKind, TokenKind};
+
+/// Parses a setcode written like `0x1a`.
+pub fn parse_setcode(value: &str) -> Result<u16, String> {
+    let digits = value.trim().strip_prefix("0x").ok_or(format!("Setcode {} should look like 0x1a.", value))?;
+    u16::from_str_radix(digits, 16).map_err(|e| format!("Bad setcode {}: {}", value, e))
+}
+
+/// A new number and/or name for one archetype.
+#[derive(Debug, Clone)]
+pub struct ArchetypeChange {
+    pub setcode: u16,
+    pub new_setcode: u16,
+    pub name: Option<String>,
+    pub new_name: Option<String>
+}
+
+impl ArchetypeChange {
+    /// Sub-archetypes share the low 12 bits with their base archetype, so they move along with it.
+    pub fn map_setcode(&self, setcode: u16) -> u16 {
+        if setcode == self.setcode { self.new_setcode }
+        else if self.setcode & 0xf000 == 0 && self.new_setcode & 0xf000 == 0 && setcode & 0x0fff == self.setcode { (setcode & 0xf000) | self.new_setcode }
+        else { setcode }
+    }
+
+    /// Changes setcodes of the cards. Returns indexes of the changed cards.
+    pub fn apply(&self, cards: &mut [Card]) -> Vec<usize> {
+        let mut changed = Vec::new();
+        for (i, card) in cards.iter_mut().enumerate() {
+            let setcodes = card.setcodes();
+            let new_setcodes = setcodes.iter().map(|s| self.map_setcode(*s)).collect::<Vec<_>>();
+            if new_setcodes != setcodes {
+                card.set_setcodes(&new_setcodes);
+                changed.push(i);
+            }
+        }
+        changed
+    }
+
+    /// Setcodes that the archetype or its sub-archetypes move to, while cards or `setnames` use them for an archetype that stays.
+    pub fn collisions(&self, cards: &[Card], setnames: impl Iterator<Item = u16>) -> Vec<u16> {
+        let used = cards.iter().flat_map(|c| c.setcodes()).chain(setnames).chain(std::iter::once(self.setcode)).collect::<HashSet<_>>();
+        let mut collisions = used.iter().filter(|s| self.map_setcode(**s) != **s).map(|s| self.map_setcode(*s))
+            .filter(|new| used.contains(new) && self.map_setcode(*new) == *new)
+            .collect::<Vec<_>>();
+        collisions.sort();
+        collisions.dedup();
+        collisions
+    }
+
+    fn renamed(&self) -> Option<(&String, &String)> {
+        match (self.name.as_ref(), self.new_name.as_ref()) {
+            (Some(name), Some(new_name)) if name != new_name => Some((name, new_name)),
+            _ => None
+        }
+    }
+
+    /// Rewrites the `!setname` lines of a strings.conf, keeping comments and the other lines.
+    pub fn rewrite_string_conf(&self, conf: &str) -> String {
+        conf.split("\n").map(|line| {
+            let parts = line.splitn(3, " ").collect::<Vec<_>>();
+            if parts.len() < 3 || parts[0] != "!setname" { return line.to_string() }
+            let Ok(setcode) = parse_setcode(parts[1]) else { return line.to_string() };
+            if self.map_setcode(setcode) == setcode && setcode != self.setcode { return line.to_string() }
+            let name = match self.renamed() {
+                Some((name, new_name)) if setcode == self.setcode => parts[2].replacen(name.as_str(), new_name, 1),
+                _ => parts[2].to_string()
+            };
+            format!("!setname 0x{:x} {}", self.map_setcode(setcode), name)
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Rewrites `系列：` lines and 「name」 mentions of the cards inside an xyyz source, leaving other lines as they are.
+    pub fn rewrite_xyyz(&self, source: &str) -> String {
+        let mut document = Document::parse(source);
+        for line in document.cards_mut().flat_map(|card| card.lines.iter_mut().skip(1)) {
+            if matches!(line.key(), Some("系列字段：" | "系列：")) {
+                let Some(value) = line.token_mut(TokenKind::Value) else { continue };
+                value.text = value.text.split("、").map(|set| self.rewrite_set(set.trim())).collect::<Vec<_>>().join("、");
+            } else if let (Some((name, new_name)), LineKind::Description | LineKind::Metadata | LineKind::Pendulum) = (self.renamed(), line.kind) {
+                for token in line.tokens.iter_mut().filter(|t| matches!(t.kind, TokenKind::Text | TokenKind::Value)) {
+                    token.text = token.text.replace(&format!("「{}」", name), &format!("「{}」", new_name));
+                }
+            }
+        }
+        document.to_string()
+    }
+
+    fn rewrite_set(&self, set: &str) -> String {
+        match parse_setcode(set) {
+            Ok(setcode) if self.map_setcode(setcode) != setcode => format!("0x{:X}", self.map_setcode(setcode)),
+            Ok(_) => set.to_string(),
+            Err(_) => match self.renamed() {
+                Some((name, new_name)) if name == set => new_name.clone(),
+                _ => set.to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::archetype::*;
+    use crate::card::CardTransformer;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_archetype_change() {
+        let change = ArchetypeChange { setcode: 0x7f0, new_setcode: 0x7f1, name: Some("测试族".to_string()), new_name: Some("检测族".to_string()) };
+        let conf = "#setnames\n!setname 0x7f0 测试族\tテスト\n!setname 0x17f0 真测试族\n!setname 0x3 其他";
+        assert_eq!(change.rewrite_string_conf(conf), "#setnames\n!setname 0x7f1 检测族\tテスト\n!setname 0x17f1 真测试族\n!setname 0x3 其他");
+        let source = "测试龙(10000) 暗 4星 龙 1600 1000\n①：把1只「测试族」怪兽加入手卡。\n系列：测试族、0x17F0、0x3\n\n测试族(10001) 通常魔法";
+        let mut cards = Xyyz::from_string(source);
+        assert_eq!(change.rewrite_xyyz(source), "测试龙(10000) 暗 4星 龙 1600 1000\n①：把1只「检测族」怪兽加入手卡。\n系列：检测族、0x17F1、0x3\n\n测试族(10001) 通常魔法");
+        assert_eq!(change.apply(&mut cards), vec![0]);
+        assert_eq!(cards[0].setcodes(), vec![0x17f1, 0x3]);
+        assert!(change.collisions(&Xyyz::from_string(source), [0x7f0, 0x17f0, 0x3].into_iter()).is_empty());
+        // Another archetype already holds the number a sub-archetype moves to.
+        let cards = Xyyz::from_string("真检测龙(10002) 通常魔法\n系列：0x17F1");
+        assert_eq!(change.collisions(&cards, [0x7f0, 0x17f0].into_iter()), vec![0x17f1]);
+        assert_eq!(change.collisions(&[], [0x17f0, 0x7f1].into_iter()), vec![0x7f1]);
+    }
+}
@@ -0,0 +1,323 @@
+//! Language server for xyyz card files, speaking LSP over stdio.
+//!
+//! Pass `{"strings": ["path/to/strings.conf"]}` as initialization options to know archetype names.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use cdb_transformer::card::{Card, CardTransformer};
+use cdb_transformer::constants::Type;
+use cdb_transformer::lint::{self, Severity};
+use cdb_transformer::references::extract_references;
+use cdb_transformer::transformers::*;
+
+/// The next message of `input`, skipping those with a malformed header or body. `None` once the input ends.
+fn read_message(input: &mut impl BufRead) -> Option<Value> {
+    loop {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            if input.read_line(&mut line).ok()? == 0 { return None }
+            let line = line.trim_end();
+            if line.is_empty() { break }
+            // Searched rather than stripped, the header may follow the unread body of a skipped message.
+            if let Some(index) = line.find("Content-Length:") {
+                length = line[index + "Content-Length:".len()..].trim().parse::<usize>().ok();
+            }
+        }
+        let Some(length) = length else { eprintln!("Skipped a message without a valid Content-Length."); continue };
+        let mut body = vec![0; length];
+        input.read_exact(&mut body).ok()?;
+        match serde_json::from_slice(&body) {
+            Ok(message) => return Some(message),
+            Err(e) => eprintln!("Skipped a malformed message: {}", e)
+        }
+    }
+}
+
+fn send(output: &mut impl Write, message: Value) {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body).expect("Write message failed");
+    output.flush().expect("Write message failed");
+}
+
+/// An open document, with where its lines start worked out once per change.
+struct Document {
+    text: String,
+    /// Char offset and byte range of every line, without the `\r` of CRLF line ends.
+    lines: Vec<(usize, Range<usize>)>
+}
+
+impl Document {
+    fn new(text: &str) -> Document {
+        let mut index = 0;
+        let mut byte = 0;
+        let lines = text.split("\n").map(|raw_line| {
+            let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+            let start = (index, byte..byte + line.len());
+            index += raw_line.chars().count() + 1;
+            byte += raw_line.len() + 1;
+            start
+        }).collect();
+        Document { text: text.to_string(), lines }
+    }
+
+    /// The char offset line `n` starts at, and its content.
+    fn line(&self, n: usize) -> (usize, &str) {
+        let (start, bytes) = &self.lines[n];
+        (*start, &self.text[bytes.clone()])
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.lines.partition_point(|(start, _)| *start <= offset).max(1) - 1
+    }
+
+    /// LSP positions count UTF-16 code units.
+    fn position(&self, offset: usize) -> Value {
+        let line = self.line_of(offset);
+        let (start, content) = self.line(line);
+        let character = content.chars().take(offset - start).map(|c| c.len_utf16()).sum::<usize>();
+        json!({ "line": line, "character": character })
+    }
+
+    fn offset(&self, position: &Value) -> usize {
+        let line = (position["line"].as_u64().unwrap_or_default() as usize).min(self.lines.len() - 1);
+        let character = position["character"].as_u64().unwrap_or_default() as usize;
+        let (start, content) = self.line(line);
+        let mut units = 0;
+        start + content.chars().take_while(|c| { units += c.len_utf16(); units <= character }).count()
+    }
+}
+
+/// The file of a `file://` URI.
+fn path_of(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        match (b, std::str::from_utf8(tail.get(..2).unwrap_or_default()).ok().and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(decoded)) => { bytes.push(decoded); rest = &tail[2..]; }
+            _ => { bytes.push(b); rest = tail; }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8_lossy(&bytes).to_string()))
+}
+
+/// The outermost 「name」 of `line` around char column `column`.
+fn reference_at(line: &str, column: usize) -> Option<String> {
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in line.chars().enumerate() {
+        match c {
+            '「' => { if depth == 0 { start = i } depth += 1; }
+            '」' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 && start <= column && column <= i {
+                    return extract_references(&line.chars().skip(start).take(i + 1 - start).collect::<String>()).pop();
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn card_at(cards: &[Card], offset: usize) -> Option<&Card> {
+    cards.iter().find(|c| c.range.is_some_and(|r| r.start <= offset && offset <= r.end))
+}
+
+fn diagnostics(uri: &str, document: &Document) -> Value {
+    let mut cards = Xyyz::from_string(&document.text);
+    for card in cards.iter_mut() {
+        card.provenance.path = Some(uri.to_string());
+    }
+    let diagnostics = lint::lint(&cards, &Default::default()).into_iter().map(|d| {
+        let range = d.range.unwrap_or((0..0).into());
+        json!({
+            "range": { "start": document.position(range.start), "end": document.position(range.end) },
+            "severity": if d.severity == Severity::Error { 1 } else { 2 },
+            "code": d.rule,
+            "source": "cdb-transformer",
+            "message": d.message
+        })
+    }).collect::<Vec<_>>();
+    json!({ "jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": { "uri": uri, "diagnostics": diagnostics } })
+}
+
+fn completion(document: &Document, position: &Value) -> Value {
+    let line = document.line_of(document.offset(position));
+    let item = |label: &str, detail: String| json!({ "label": label, "kind": 12, "detail": detail });
+    if document.line(line).1.starts_with("系列") {
+        return json!(SET_NAMES.load().iter().map(|(code, name)| item(name, format!("0x{:X}", code))).collect::<Vec<_>>())
+    }
+    // Only headers hold attributes, races and types, a line outside every card may be a header being written.
+    let start = document.line(line).0;
+    let cards = Xyyz::from_string(&document.text);
+    if cards.iter().filter_map(|c| c.range).any(|r| r.start < start && start <= r.end) { return json!([]) }
+    let items = ATTRIBUTE_NAMES.entries().map(|(bits, name)| item(name, format!("属性 0x{:X}", bits)))
+        .chain(RACE_NAMES.entries().map(|(bits, name)| item(name, format!("种族 0x{:X}", bits))))
+        .chain(TYPE_NAMES.entries().map(|(bits, name)| item(name, format!("类型 0x{:X}", bits))))
+        .chain(OT_NAMES.entries().map(|(bits, name)| item(name, format!("OT 0x{:X}", bits))))
+        .collect::<Vec<_>>();
+    json!(items)
+}
+
+fn hover(document: &Document, position: &Value) -> Value {
+    let cards = Xyyz::from_string(&document.text);
+    let Some(card) = card_at(&cards, document.offset(position)) else { return Value::Null };
+    let fields = [
+        ("type", format!("0x{:X} {}", card._type.bits(),
+            if card._type.contains(Type::Monster) { Xyyz::format_subtype(&card._type) } else { Xyyz::format_type(&card._type) })),
+        ("attribute", format!("0x{:X} {}", card.attribute.bits(), Xyyz::format_attribute(&card.attribute))),
+        ("race", format!("0x{:X} {}", card.race.bits(), Xyyz::format_race(&card.race))),
+        ("level", card.level.to_string()),
+        ("ot", format!("0x{:X} {}", card.ot.bits(), Xyyz::format_ot(&card.ot))),
+        ("setcode", format!("0x{:X}", card.setcode)),
+        ("category", format!("0x{:X}", card.category.bits()))
+    ];
+    let table = fields.iter().map(|(name, value)| format!("| {} | {} |", name, value)).collect::<Vec<_>>().join("\n");
+    let value = format!("**{}** ({})\n\n| field | value |\n| --- | --- |\n{}\n\n```sql\n{}\n```", card.name, card.code, table, SQL::to_string(card));
+    json!({ "contents": { "kind": "markdown", "value": value } })
+}
+
+/// Cards named like the 「reference」 at `position`, in the open documents and the files the document includes.
+fn definition(documents: &HashMap<String, Document>, uri: &str, position: &Value) -> Value {
+    let Some(document) = documents.get(uri) else { return Value::Null };
+    let offset = document.offset(position);
+    let (start, line) = document.line(document.line_of(offset));
+    let Some(name) = reference_at(line, offset - start) else { return Value::Null };
+    let locations = documents.iter().map(|(uri, document)| (uri.clone(), document))
+        .chain(included(documents, uri).iter().map(|(uri, document)| (uri.clone(), document)))
+        .flat_map(|(uri, document)| {
+            Xyyz::from_string(&document.text).into_iter().filter(|c| c.name == name).filter_map(|c| c.range).map(|r| {
+                json!({ "uri": uri, "range": { "start": document.position(r.start), "end": document.position(r.start) } })
+            }).collect::<Vec<_>>()
+        }).collect::<Vec<_>>();
+    json!(locations)
+}
+
+/// The files `uri` includes, directly or not, read from disk unless they are open.
+fn included(documents: &HashMap<String, Document>, uri: &str) -> Vec<(String, Document)> {
+    let open = documents.keys().filter_map(|uri| path_of(uri).map(|p| p.canonicalize().unwrap_or(p))).collect::<HashSet<_>>();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(path_of(uri), documents[uri].text.clone())];
+    let mut result = Vec::new();
+    while let Some((path, text)) = pending.pop() {
+        let Some(path) = path else { continue };
+        for (_, include) in Xyyz::includes(&text) {
+            let included = path.parent().unwrap_or(Path::new("")).join(include);
+            let included = included.canonicalize().unwrap_or(included);
+            if open.contains(&included) || !visited.insert(included.clone()) { continue }
+            let Ok(text) = std::fs::read_to_string(&included) else { continue };
+            result.push((format!("file://{}", included.display()), Document::new(&text)));
+            pending.push((Some(included), text));
+        }
+    }
+    result
+}
+
+fn formatting(document: &Document) -> Value {
+    let formatted = Xyyz::format_source(&document.text);
+    if formatted == document.text { return json!([]) }
+    json!([{ "range": { "start": document.position(0), "end": document.position(document.text.chars().count()) }, "newText": formatted }])
+}
+
+fn main() {
+    let mut input = std::io::stdin().lock();
+    let mut output = std::io::stdout().lock();
+    let mut documents = HashMap::<String, Document>::new();
+    let empty = Document::new("");
+    while let Some(message) = read_message(&mut input) {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        let result = match message["method"].as_str().unwrap_or_default() {
+            "initialize" => {
+                let strings = params["initializationOptions"]["strings"].as_array().cloned().unwrap_or_default();
+                read_string_conf(&strings.iter().filter_map(|s| s.as_str()).collect::<Vec<_>>());
+                json!({ "capabilities": {
+                    "textDocumentSync": 1,
+                    "completionProvider": { "triggerCharacters": [" ", "/", "：", "、"] },
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentFormattingProvider": true
+                } })
+            }
+            "textDocument/didOpen" | "textDocument/didChange" => {
+                let text = params["textDocument"]["text"].as_str()
+                    .or(params["contentChanges"].as_array().and_then(|c| c.last()).and_then(|c| c["text"].as_str()))
+                    .unwrap_or_default();
+                let document = Document::new(text);
+                send(&mut output, diagnostics(&uri, &document));
+                documents.insert(uri.clone(), document);
+                continue;
+            }
+            "textDocument/didClose" => { documents.remove(&uri); continue; }
+            "textDocument/completion" => completion(documents.get(&uri).unwrap_or(&empty), &params["position"]),
+            "textDocument/hover" => hover(documents.get(&uri).unwrap_or(&empty), &params["position"]),
+            "textDocument/definition" => definition(&documents, &uri, &params["position"]),
+            "textDocument/formatting" => formatting(documents.get(&uri).unwrap_or(&empty)),
+            "shutdown" => Value::Null,
+            "exit" => break,
+            _ if message.get("id").is_some() => {
+                send(&mut output, json!({ "jsonrpc": "2.0", "id": message["id"], "error": { "code": -32601, "message": "Method not found" } }));
+                continue;
+            }
+            _ => continue
+        };
+        if message.get("id").is_some() {
+            send(&mut output, json!({ "jsonrpc": "2.0", "id": message["id"], "result": result }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let document = Document::new("原质😀(1) 通常魔法\r\n把「原质😀」加入手卡。");
+        assert_eq!(document.position(15), json!({ "line": 1, "character": 2 }));
+        assert_eq!(document.offset(&json!({ "line": 0, "character": 4 })), 3);
+        assert_eq!(document.line(1), (13, "把「原质😀」加入手卡。"));
+        assert_eq!(reference_at("把「原质😀」加入手卡。", 3), Some("原质😀".to_string()));
+        assert_eq!(reference_at("把「原质😀」加入手卡。", 7), None);
+    }
+
+    #[test]
+    fn test_messages() {
+        let body = r#"{"method":"exit"}"#;
+        let input = format!("Content-Length: x\r\n\r\n{{}}Content-Length: 2\r\n\r\n{{xContent-Length: {}\r\n\r\n{}", body.len(), body);
+        let mut input = std::io::Cursor::new(input);
+        assert_eq!(read_message(&mut input), Some(json!({ "method": "exit" })));
+        assert_eq!(read_message(&mut input), None);
+    }
+
+    #[test]
+    fn test_completion() {
+        let document = Document::new("原质(1) 通常魔法\n抽卡。\n\n原质贝塔(2) 通常魔法\n\n");
+        let at = |line: usize| completion(&document, &json!({ "line": line, "character": 0 })).as_array().unwrap().len();
+        assert!(at(0) > 0);
+        assert_eq!(at(1), 0);
+        assert!(at(3) > 0);
+        assert!(at(5) > 0);
+    }
+
+    #[test]
+    fn test_definition() {
+        let dir = std::env::temp_dir().join(format!("xyyz_lsp_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.txt"), "原质贝塔(2) 通常魔法\n").unwrap();
+        let uri = format!("file://{}", dir.join("a%20b.txt").display()).replace("a%20b", "a%2520b");
+        assert_eq!(path_of(&uri), Some(dir.join("a%20b.txt")));
+        let documents = HashMap::from([(uri.clone(), Document::new("#include b.txt\n原质阿尔法(1) 通常魔法\n把「原质贝塔」加入手卡。"))]);
+        let locations = definition(&documents, &uri, &json!({ "line": 2, "character": 3 }));
+        assert_eq!(locations, json!([{ "uri": format!("file://{}", dir.join("b.txt").canonicalize().unwrap().display()),
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } } }]));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -12,11 +12,21 @@ pub struct PackInfo {
 	pub pack_id: String,
 	pub pack: String,
 	pub rarity: Vec<String>,
-	pub date: String
+	pub date: String,
+	/// The name printed in the pack, e.g. the Japanese name of an OCG card.
+	pub japanese_name: String
+}
+
+/// How many copies of a card a forbidden/limited list allows.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Limit {
+	pub list: String,
+	pub count: u8
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Range {
 	pub start: usize,
 	pub end: usize
@@ -28,14 +38,53 @@ impl From<std::ops::Range<usize>> for Range {
 	}
 }
 
+/// Lines and columns a card covers in a text source, both 1-based, columns counted in chars.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct Span {
+	pub start_line: usize,
+	pub start_column: usize,
+	pub end_line: usize,
+	pub end_column: usize
+}
+
+/// Where a card was read from.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Provenance {
+	pub path: Option<String>,
+	pub format: Option<Format>,
+	pub span: Option<Span>,
+	/// Row id in the datas table, for cards read from a cdb.
+	pub row: Option<i64>
+}
+
+impl Provenance {
+	pub fn new(format: Format) -> Provenance {
+		Provenance { format: Some(format), ..Default::default() }
+	}
+}
+
+impl std::fmt::Display for Provenance {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let source = self.path.clone().or(self.format.map(|f| f.to_string())).unwrap_or("<unknown>".to_string());
+		match (self.span, self.row) {
+			(Some(span), _) => write!(f, "{}:{}:{}", source, span.start_line, span.start_column),
+			(None, Some(row)) => write!(f, "{} row {}", source, row),
+			(None, None) => write!(f, "{}", source)
+		}
+	}
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Card {
     pub code: u32,
 	pub name: String,
 	pub desc: String,
 	pub alias: u32,
 	pub setcode: u64,
+	pub extra_setcodes: Vec<u16>,
 	#[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
 	pub _type: Type,
 	pub level: u32,
@@ -45,6 +94,7 @@ pub struct Card {
 	pub race: Race,
 	pub attack: i32,
 	pub defense: i32,
+	pub maximum_attack: i32,
 	pub lscale: u32,
 	pub rscale: u32,
 	#[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
@@ -55,7 +105,22 @@ pub struct Card {
 	pub category: Category,
 	pub texts: Vec<String>,
 	pub pack: Option<PackInfo>,
-	pub range: Option<Range>
+	/// Printings of the card other than `pack`, like its reprints.
+	#[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+	pub reprints: Vec<PackInfo>,
+	pub range: Option<Range>,
+	/// Where each line of `desc` is in an xyyz source, like `range`.
+	#[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+	pub desc_ranges: Vec<Range>,
+	pub provenance: Provenance,
+	/// Comment lines and section headers written right before this card in an xyyz file.
+	pub comments: Vec<String>,
+	/// Comment lines inside this card in an xyyz file, each after the given number of lines of the card.
+	#[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+	pub inner_comments: Vec<(usize, String)>,
+	/// Status in the lflist.conf lists read, except unlimited ones, or as a source writes it, where `无限制` lifts a limit.
+	#[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+	pub limits: Vec<Limit>
 }
 
 impl Card {
@@ -66,12 +131,14 @@ impl Card {
 			desc: String::new(),
 			alias: 0,
 			setcode: 0,
+			extra_setcodes: Vec::new(),
 			_type: Type::empty(),
 			level: 0,
 			attribute: Attribute::empty(),
 			race: Race::empty(),
 			attack: 0,
 			defense: 0,
+			maximum_attack: 0,
 			lscale: 0,
 			rscale: 0,
 			link_marker: Linkmarkers::empty(),
@@ -79,26 +146,52 @@ impl Card {
 			category: Category::empty(),
 			texts: Vec::new(),
 			pack: None,
-			range: None
+			reprints: Vec::new(),
+			range: None,
+			desc_ranges: Vec::new(),
+			provenance: Provenance::default(),
+			comments: Vec::new(),
+			inner_comments: Vec::new(),
+			limits: Vec::new()
 		};
 	}
+
+	/// `pack` and then the reprints.
+	pub fn printings(&self) -> impl Iterator<Item = &PackInfo> {
+		self.pack.iter().chain(self.reprints.iter())
+	}
+
+	/// Every archetype of this card, the four packed into `setcode` first.
+	pub fn setcodes(&self) -> Vec<u16> {
+		(0..4).map(|i| (self.setcode >> (i * 16)) as u16)
+			.filter(|set| *set > 0)
+			.chain(self.extra_setcodes.iter().copied())
+			.collect()
+	}
+
+	/// Packs the first four archetypes into `setcode` and keeps the rest in `extra_setcodes`.
+	pub fn set_setcodes(&mut self, setcodes: &[u16]) {
+		let setcodes = setcodes.iter().copied().filter(|set| *set > 0).collect::<Vec<_>>();
+		self.setcode = setcodes.iter().take(4).enumerate().fold(0, |code, (i, set)| code | (*set as u64) << (i * 16));
+		self.extra_setcodes = setcodes.into_iter().skip(4).collect();
+	}
 }
 
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 impl Card {
 	#[wasm_bindgen(getter = type)]
-	pub fn _type(&self) -> u32 { self._type.bits() }
+	pub fn _type(&self) -> u64 { self._type.bits() }
 	#[wasm_bindgen(setter = type)]
-	pub fn set_type(&mut self, v: u32) { self._type = Type::from_bits_retain(v) }
+	pub fn set_type(&mut self, v: u64) { self._type = Type::from_bits_retain(v) }
 	#[wasm_bindgen(getter)]
 	pub fn attribute(&self) -> u32 { self.attribute.bits() }
 	#[wasm_bindgen(setter)]
 	pub fn set_attribute(&mut self, v: u32) { self.attribute = Attribute::from_bits_retain(v) }
 	#[wasm_bindgen(getter)]
-	pub fn race(&self) -> u32 { self.race.bits() }
+	pub fn race(&self) -> u64 { self.race.bits() }
 	#[wasm_bindgen(setter)]
-	pub fn set_race(&mut self, v: u32) { self.race = Race::from_bits_retain(v) }
+	pub fn set_race(&mut self, v: u64) { self.race = Race::from_bits_retain(v) }
 	#[wasm_bindgen(getter)]
 	pub fn linkmarker(&self) -> i32 { self.link_marker.bits() }
 	#[wasm_bindgen(setter)]
@@ -27,15 +27,45 @@ bitflags! {
         const TCG = 2;
         const Custom = 4;
         const SC = 8;
+        const Anime = 16;
+        const Illegal = 32;
+        const VideoGame = 64;
+        const Speed = 128;
+        const Prerelease = 256;
+        const Rush = 512;
+        const Hidden = 2048;
 
         const Draft = 1024;
     }
 }
 
+impl OT {
+    /// Flags the `ot` column of ygopro databases knows, and the draft mark of this tool.
+    pub const YGOPRO: OT = OT::OCG.union(OT::TCG).union(OT::Custom).union(OT::SC).union(OT::Draft);
+}
+
+bitflags! {
+    /// `ot` column of EDOPro databases.
+    #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+    pub struct Scope: u64 {
+        const OCG = 0x1;
+        const TCG = 0x2;
+        const Anime = 0x4;
+        const Illegal = 0x8;
+        const VideoGame = 0x10;
+        const Custom = 0x20;
+        const Speed = 0x40;
+        const Prerelease = 0x100;
+        const Rush = 0x200;
+        const Legend = 0x400;
+        const Hidden = 0x1000;
+    }
+}
+
 bitflags! {
     #[cfg_attr(target_arch="wasm32",wasm_bindgen)]
     #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
-    pub struct Race: u32 {
+    pub struct Race: u64 {
         const Warrior = 1;
         const Spellcaster = 2;
         const Fairy = 4;
@@ -62,13 +92,21 @@ bitflags! {
         const Wyrm = 8388608;
         const Cybers = 16777216;
         const Illusion = 33554432;
+        const Cyborg = 67108864;
+        const MagicalKnight = 134217728;
+        const HighDragon = 268435456;
+        const OmegaPsychic = 536870912;
+        const CelestialWarrior = 1073741824;
+        const Galaxy = 2147483648;
+        const Yokai = 0x4000000000000000;
+        const Charisma = 0x8000000000000000;
     }
 }
 
 bitflags! {
     #[cfg_attr(target_arch="wasm32",wasm_bindgen)]
     #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
-    pub struct Type: u32 {
+    pub struct Type: u64 {
         const Monster = 1;
         const Spell = 2;
         const Trap = 4;
@@ -94,6 +132,13 @@ bitflags! {
         const Pendulum = 16777216;
         const Spsummon = 33554432;
         const Link = 67108864;
+        const Skill = 134217728;
+        const Action = 268435456;
+        /// Rush Duel marks of this tool, no client reads them from `datas.type`.
+        /// Databases keep Maximum with a mark of this tool above bit 32 of `level`, see `SQL::read_maximum`,
+        /// and Legend as `Scope::Legend` of EDOPro.
+        const Maximum = 536870912;
+        const Legend = 1073741824;
     }
 }
 
@@ -116,38 +161,38 @@ bitflags! {
     #[cfg_attr(target_arch="wasm32",wasm_bindgen)]
     #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
     pub struct Category: u64 {
-        const category1 = 0x1;
-        const category2 = 0x2;
-        const category3 = 0x4;
-        const category4 = 0x8;
-        const category5 = 0x10;
-        const category6 = 0x20;
-        const category7 = 0x40;
-        const category8 = 0x80;
-        const category9 = 0x100;
-        const category10 = 0x200;
-        const category11 = 0x400;
-        const category12 = 0x800;
-        const category13 = 0x1000;
-        const category14 = 0x2000;
-        const category15 = 0x4000;
-        const category16 = 0x8000;
-        const category17 = 0x10000;
-        const category18 = 0x20000;
-        const category19 = 0x40000;
-        const category20 = 0x80000;
-        const category21 = 0x100000;
-        const category22 = 0x200000;
-        const category23 = 0x400000;
-        const category24 = 0x800000;
-        const category25 = 0x1000000;
-        const category26 = 0x2000000;
-        const category27 = 0x4000000;
-        const category28 = 0x8000000;
-        const category29 = 0x10000000;
-        const category30 = 0x20000000;
-        const category31 = 0x40000000;
-        const category32 = 0x80000000;
+        const DestroySpellTrap = 0x1;
+        const DestroyMonster = 0x2;
+        const Banish = 0x4;
+        const SendToGrave = 0x8;
+        const ReturnToHand = 0x10;
+        const ReturnToDeck = 0x20;
+        const DestroyHand = 0x40;
+        const DestroyDeck = 0x80;
+        const Draw = 0x100;
+        const Search = 0x200;
+        const Recycle = 0x400;
+        const Position = 0x800;
+        const Control = 0x1000;
+        const ChangeAtkDef = 0x2000;
+        const Piercing = 0x4000;
+        const MultiAttack = 0x8000;
+        const AttackLimit = 0x10000;
+        const DirectAttack = 0x20000;
+        const SpecialSummon = 0x40000;
+        const Token = 0x80000;
+        const RaceRelated = 0x100000;
+        const AttributeRelated = 0x200000;
+        const Damage = 0x400000;
+        const Recover = 0x800000;
+        const DestroyResistance = 0x1000000;
+        const EffectResistance = 0x2000000;
+        const Counter = 0x4000000;
+        const Gamble = 0x8000000;
+        const FusionRelated = 0x10000000;
+        const SynchroRelated = 0x20000000;
+        const XyzRelated = 0x40000000;
+        const Negate = 0x80000000;
     }
 }
 
@@ -172,3 +217,51 @@ impl ToString for Format {
         }.to_string()
     }
 }
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum Dialect {
+    #[default]
+    Ygopro,
+    #[value(name = "edopro")]
+    EDOPro
+}
+
+/// Where a card is sold, for release dates.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Region {
+    #[value(name = "ocg")]
+    #[serde(rename = "OCG")]
+    Ocg,
+    #[value(name = "tcg")]
+    #[serde(rename = "TCG")]
+    Tcg
+}
+
+impl Region {
+    pub fn ot(&self) -> OT {
+        match self {
+            Region::Ocg => OT::OCG,
+            Region::Tcg => OT::TCG
+        }
+    }
+
+    /// The region of a printing like `SD1-JP001`, if its pack ID tells.
+    pub fn of_pack_id(pack_id: &str) -> Option<Region> {
+        if pack_id.contains("-JP") { Some(Region::Ocg) }
+        else if pack_id.contains("-EN") { Some(Region::Tcg) }
+        else { None }
+    }
+}
+
+/// How xyyz headers are written.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub enum HeaderStyle {
+    /// `骄傲与灵魂之龙(100000000) 暗 8星 ...`
+    #[default]
+    Code,
+    /// `INFO-JP000(QCSER)誇りと魂の龍(骄傲与灵魂之龙)(100000000) 暗 8星 ...`, the original XYZ format, with the code if there is one.
+    Original
+}
@@ -0,0 +1,397 @@
+//! Lossless concrete syntax tree of xyyz sources.
+//!
+//! Every byte of the source ends up in exactly one token, so printing a [`Document`] gives back the
+//! source it was parsed from. Tokens can be edited in place to rewrite a single field of a card
+//! without touching the spelling of anything else.
+//!
+//! Renumbering, archetype changes, code allocation, glossary fixes, formatting, the editor and
+//! card spans all rewrite or locate cards through this tree.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::card::{Card, CardTransformer};
+use crate::transformers::{Xyyz, FIELD_PREFIXES, HEADER_REGEX, HEADER_WEAK_REGEX, ORIGINAL_HEADER_REGEX, PARTS_REGEX, PENDULUM_REGEX, SECTION_REGEX};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// `[SD1-JP001]` in front of a header, or the `INFO-JP000` of an original XYZ header.
+    Pack,
+    /// `QCSER` of an original XYZ header.
+    Rarity,
+    /// The name before the Chinese name of an original XYZ header.
+    JapaneseName,
+    Name,
+    Code,
+    Alias,
+    Attribute,
+    Level,
+    Race,
+    /// One of the `/`-separated subtypes after the race, e.g. `通常`.
+    Subtype,
+    /// The type of a spell or trap, e.g. `速攻魔法`.
+    Type,
+    Attack,
+    Defense,
+    LinkMarkers,
+    MaximumAttack,
+    /// The text inside the trailing parentheses of a header, e.g. `Custom`.
+    Ot,
+    /// A number of the pendulum scale line.
+    Scale,
+    /// The prefix of a metadata line, e.g. `系列：`.
+    Key,
+    /// The content of a metadata line.
+    Value,
+    Text,
+    Comment,
+    Section,
+    /// Whitespace and punctuation between the other tokens.
+    Trivia
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineKind {
+    Header,
+    Pendulum,
+    Metadata,
+    Description,
+    Comment,
+    Section,
+    Blank
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub kind: LineKind,
+    pub tokens: Vec<Token>,
+    /// `"\n"`, `"\r\n"`, or nothing for the last line.
+    pub ending: String
+}
+
+/// A header and the lines that belong to its card, up to the last description or metadata line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardNode {
+    pub lines: Vec<Line>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Card(CardNode),
+    /// Comments, sections and blank lines between cards.
+    Line(Line)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    pub items: Vec<Item>
+}
+
+impl Line {
+    pub fn text(&self) -> String {
+        self.tokens.iter().map(|t| t.text.as_str()).collect()
+    }
+
+    pub fn token(&self, kind: TokenKind) -> Option<&Token> {
+        self.tokens.iter().find(|t| t.kind == kind)
+    }
+
+    pub fn token_mut(&mut self, kind: TokenKind) -> Option<&mut Token> {
+        self.tokens.iter_mut().find(|t| t.kind == kind)
+    }
+
+    /// The key of a metadata line, e.g. `系列：`.
+    pub fn key(&self) -> Option<&str> {
+        self.token(TokenKind::Key).map(|t| t.text.as_str())
+    }
+
+    fn parse(line: &str, ending: &str, header_name: Option<&str>) -> Line {
+        let (kind, fields) = match header_name {
+            Some(name) => (LineKind::Header, header_fields(line, name)),
+            None if line.trim().is_empty() => (LineKind::Blank, Vec::new()),
+            None if line.starts_with("#") => (LineKind::Comment, vec![(0, line.len(), TokenKind::Comment)]),
+            None if SECTION_REGEX.is_match(line) => (LineKind::Section, vec![(0, line.len(), TokenKind::Section)]),
+            None => if let Some(groups) = PENDULUM_REGEX.captures(line) {
+                (LineKind::Pendulum, [1, 2].iter().filter_map(|i| groups.get(*i)).map(|m| (m.start(), m.end(), TokenKind::Scale)).collect())
+            } else if let Some(prefix) = FIELD_PREFIXES.iter().find(|p| line.starts_with(*p)) {
+                (LineKind::Metadata, vec![(0, prefix.len(), TokenKind::Key), (prefix.len(), line.len(), TokenKind::Value)])
+            } else {
+                (LineKind::Description, vec![(0, line.len(), TokenKind::Text)])
+            }
+        };
+        Line { kind, tokens: tokenize(line, fields), ending: ending.to_string() }
+    }
+}
+
+/// Cuts `line` into the given fields, filling the gaps between them with trivia.
+fn tokenize(line: &str, mut fields: Vec<(usize, usize, TokenKind)>) -> Vec<Token> {
+    fields.retain(|(start, end, _)| start < end);
+    fields.sort_by_key(|(start, _, _)| *start);
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    let mut push = |kind, text: &str| if !text.is_empty() { tokens.push(Token { kind, text: text.to_string() }) };
+    for (start, end, kind) in fields {
+        if start < index { continue }
+        push(TokenKind::Trivia, &line[index..start]);
+        push(kind, &line[start..end]);
+        index = end;
+    }
+    push(TokenKind::Trivia, &line[index..]);
+    tokens
+}
+
+/// Byte ranges of the fields of a header line whose card is called `name`.
+fn header_fields(line: &str, name: &str) -> Vec<(usize, usize, TokenKind)> {
+    let mut fields = Vec::new();
+    let mut field = |m: Option<regex::Match>, offset: usize, kind| if let Some(m) = m { fields.push((offset + m.start(), offset + m.end(), kind)) };
+    let (parts_start, parts) = if let Some(groups) = ORIGINAL_HEADER_REGEX.captures(line) {
+        for (i, kind) in [(1, TokenKind::Pack), (2, TokenKind::Rarity), (3, TokenKind::JapaneseName), (4, TokenKind::Name), (6, TokenKind::Code), (8, TokenKind::Alias), (11, TokenKind::Ot)] {
+            field(groups.get(i), 0, kind);
+        }
+        let parts = groups.get(9).unwrap();
+        (parts.start(), parts.as_str())
+    } else if let Some(groups) = HEADER_REGEX.captures(line) {
+        field(groups.get(2), 0, TokenKind::Name);
+        field(groups.get(3), 0, TokenKind::Code);
+        field(groups.get(5), 0, TokenKind::Alias);
+        field(groups.get(8), 0, TokenKind::Ot);
+        let pack = groups.get(1);
+        field(pack, 0, TokenKind::Pack);
+        let parts = groups.get(6).unwrap();
+        (parts.start(), parts.as_str())
+    } else if let Some(groups) = HEADER_WEAK_REGEX.captures(line) {
+        field(groups.get(8), 0, TokenKind::Ot);
+        field(groups.get(1), 0, TokenKind::Pack);
+        let mix = groups.get(2).unwrap();
+        let name_end = if mix.as_str().starts_with(name) { mix.start() + name.len() } else { mix.start() };
+        fields.push((mix.start(), name_end, TokenKind::Name));
+        let rest = &line[name_end..mix.end()];
+        (name_end + rest.len() - rest.trim_start().len(), rest.trim())
+    } else {
+        return fields
+    };
+    // The pack capture swallows the whitespace after the brackets.
+    for (start, end, kind) in fields.iter_mut() {
+        if *kind == TokenKind::Pack { *end = *start + line[*start..*end].trim_end().len() }
+    }
+    let mut field = |start: usize, end: usize, kind| fields.push((parts_start + start, parts_start + end, kind));
+    match PARTS_REGEX.captures(parts) {
+        Some(groups) => {
+            for (i, kind) in [(1, TokenKind::Attribute), (2, TokenKind::Level), (3, TokenKind::Race), (5, TokenKind::Attack), (8, TokenKind::MaximumAttack)] {
+                if let Some(m) = groups.get(i) { field(m.start(), m.end(), kind) }
+            }
+            match (groups.get(7), groups.get(6)) {
+                (Some(m), _) => field(m.start(), m.end(), TokenKind::LinkMarkers),
+                (None, Some(m)) => field(m.start(), m.end(), TokenKind::Defense),
+                _ => {}
+            }
+            if let Some(m) = groups.get(4) {
+                let mut index = m.start();
+                for subtype in m.as_str().split('/') {
+                    field(index, index + subtype.len(), TokenKind::Subtype);
+                    index += subtype.len() + 1;
+                }
+            }
+        }
+        None => field(0, parts.len(), TokenKind::Type)
+    }
+    fields
+}
+
+impl CardNode {
+    pub fn header(&self) -> &Line {
+        &self.lines[0]
+    }
+
+    pub fn code(&self) -> u32 {
+        self.get(TokenKind::Code).and_then(|c| c.parse().ok()).unwrap_or_default()
+    }
+
+    /// The text of the first token of `kind` in the card.
+    pub fn get(&self, kind: TokenKind) -> Option<&str> {
+        self.lines.iter().find_map(|l| l.token(kind)).map(|t| t.text.as_str())
+    }
+
+    /// Replaces the text of the first token of `kind`, returning whether there was one.
+    pub fn set(&mut self, kind: TokenKind, text: &str) -> bool {
+        match self.lines.iter_mut().find_map(|l| l.tokens.iter_mut().find(|t| t.kind == kind)) {
+            Some(token) => { token.text = text.to_string(); true }
+            None => false
+        }
+    }
+
+    /// The value of the metadata line starting with `key`, e.g. `系列：`.
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata_line(key).and_then(|i| self.lines[i].token(TokenKind::Value)).map(|t| t.text.as_str())
+    }
+
+    /// Rewrites the metadata line starting with `key`, or adds one after the last line of the card.
+    pub fn set_metadata(&mut self, key: &str, value: &str) {
+        let tokens = vec![Token { kind: TokenKind::Key, text: key.to_string() }, Token { kind: TokenKind::Value, text: value.to_string() }];
+        if let Some(i) = self.metadata_line(key) { return self.lines[i].tokens = tokens }
+        // The new line takes over the ending of the old last line, which may be the end of the file.
+        let line_ending = match self.header().ending.as_str() { "" => "\n".to_string(), ending => ending.to_string() };
+        let last = self.lines.last_mut().unwrap();
+        let ending = std::mem::replace(&mut last.ending, line_ending);
+        self.lines.push(Line { kind: LineKind::Metadata, tokens, ending })
+    }
+
+    /// Removes the metadata line starting with `key`, returning whether there was one.
+    pub fn remove_metadata(&mut self, key: &str) -> bool {
+        let Some(i) = self.metadata_line(key) else { return false };
+        let line = self.lines.remove(i);
+        if i == self.lines.len() { self.lines[i - 1].ending = line.ending }
+        true
+    }
+
+    fn metadata_line(&self, key: &str) -> Option<usize> {
+        self.lines.iter().position(|l| l.kind == LineKind::Metadata && l.token(TokenKind::Key).is_some_and(|t| t.text == key))
+    }
+
+    /// Writes `code` into the header, adding `(code)` after the name of a header without one.
+    pub fn set_code(&mut self, code: u32) {
+        if self.set(TokenKind::Code, &code.to_string()) { return }
+        let header = self.header().clone();
+        let Some(i) = header.tokens.iter().position(|t| t.kind == TokenKind::Name) else { return };
+        // The Chinese name of an original XYZ header is in parentheses, the code goes after them.
+        let closing = header.tokens.get(i + 1).is_some_and(|t| t.kind == TokenKind::Trivia && t.text.starts_with(')'));
+        let mut text = header.text();
+        text.insert_str(header.tokens[..=i].iter().map(|t| t.text.len()).sum::<usize>() + closing as usize, &format!("({})", code));
+        self.lines[0] = Line::parse(&text, &header.ending, Some(&header.tokens[i].text));
+    }
+
+    /// Rewrites the whole card from `card`, in the line endings it was written with. Comments before the card are not its own.
+    pub fn replace(&mut self, card: &Card) {
+        let ending = self.lines.iter().map(|l| l.ending.as_str()).find(|e| !e.is_empty()).unwrap_or("\n").to_string();
+        let last_ending = self.lines.last().map(|l| l.ending.clone()).unwrap_or_default();
+        let mut card = card.clone();
+        card.comments.clear();
+        let document = Document::parse(&Xyyz::to_string(&card).replace("\n", &ending));
+        let lines = document.items.into_iter().flat_map(|item| match item {
+            Item::Card(card) => card.lines,
+            Item::Line(line) => vec![line]
+        }).collect::<Vec<_>>();
+        if lines.first().is_none_or(|l| l.kind != LineKind::Header) { return }
+        self.lines = lines;
+        self.lines.last_mut().unwrap().ending = last_ending;
+    }
+
+    pub fn to_card(&self) -> Option<Card> {
+        Xyyz::from_string(&self.to_string()).pop()
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.tokens.iter().try_for_each(|t| f.write_str(&t.text))?;
+        f.write_str(&self.ending)
+    }
+}
+
+impl Display for CardNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.lines.iter().try_for_each(|l| l.fmt(f))
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.items.iter().try_for_each(|item| match item {
+            Item::Card(card) => card.fmt(f),
+            Item::Line(line) => line.fmt(f)
+        })
+    }
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Document {
+        let mut raw_lines = Vec::new();
+        let mut index = 0;
+        let mut headers = HashMap::new();
+        let cards = Xyyz::from_string(text);
+        let starts = cards.iter().filter_map(|c| c.range.map(|r| (r.start, c.name.as_str()))).collect::<HashMap<_, _>>();
+        for raw_line in text.split_inclusive("\n") {
+            let content = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+            let content = content.strip_suffix('\r').unwrap_or(content);
+            if let Some(name) = starts.get(&index) { headers.insert(raw_lines.len(), *name); }
+            raw_lines.push((content, &raw_line[content.len()..]));
+            index += raw_line.chars().count() - raw_line.ends_with('\n') as usize + 1;
+        }
+        let lines = raw_lines.iter().enumerate()
+            .map(|(i, (content, ending))| Line::parse(content, ending, headers.get(&i).copied()))
+            .collect::<Vec<_>>();
+        let mut items = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            if lines[i].kind != LineKind::Header { items.push(Item::Line(lines[i].clone())); i += 1; continue }
+            let next = (i + 1..lines.len()).find(|j| lines[*j].kind == LineKind::Header).unwrap_or(lines.len());
+            let end = (i + 1..next).rev()
+                .find(|j| matches!(lines[*j].kind, LineKind::Description | LineKind::Metadata | LineKind::Pendulum))
+                .unwrap_or(i) + 1;
+            items.push(Item::Card(CardNode { lines: lines[i..end].to_vec() }));
+            i = end;
+        }
+        Document { items }
+    }
+
+    pub fn cards(&self) -> impl Iterator<Item = &CardNode> {
+        self.items.iter().filter_map(|item| match item { Item::Card(card) => Some(card), _ => None })
+    }
+
+    pub fn cards_mut(&mut self) -> impl Iterator<Item = &mut CardNode> {
+        self.items.iter_mut().filter_map(|item| match item { Item::Card(card) => Some(card), _ => None })
+    }
+
+    pub fn card_mut(&mut self, code: u32) -> Option<&mut CardNode> {
+        self.cards_mut().find(|c| c.code() == code)
+    }
+
+    pub fn to_cards(&self) -> Vec<Card> {
+        Xyyz::from_string(&self.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cst::*;
+
+    #[test]
+    fn test_round_trip() {
+        for text in [include_str!("transformers/test_data/xyyz.txt"), include_str!("transformers/test_data/xyyz_weak.txt")] {
+            assert_eq!(Document::parse(text).to_string(), text);
+            assert_eq!(Document::parse(&text.replace("\n", "\r\n")).to_string(), text.replace("\n", "\r\n"));
+        }
+        let text = "# 注释\r\n[SD1-JP001]  原质阿尔法(10000) 暗 3星 恶魔/通常/调整 1000 1000 (OCG)\r\n起点之果。\r\n\r\n#尾注\r\n日食爆龙 暗 LINK-2 龙/连接 1600 [↓][↘]\n光属性龙族怪兽+暗属性龙族怪兽";
+        let mut document = Document::parse(text);
+        assert_eq!(document.to_string(), text);
+        assert_eq!(document.cards().count(), 2);
+        assert_eq!(document.items.len(), 5);
+        let card = document.card_mut(10000).unwrap();
+        assert_eq!(card.get(TokenKind::Pack), Some("[SD1-JP001]"));
+        assert_eq!(card.header().tokens.iter().filter(|t| t.kind == TokenKind::Subtype).count(), 2);
+        assert_eq!(card.get(TokenKind::Ot), Some("OCG"));
+        card.set(TokenKind::Attack, "1500");
+        card.set_metadata("效果分类：", "0x1");
+        let link = document.cards().nth(1).unwrap();
+        assert_eq!(link.get(TokenKind::Name), Some("日食爆龙"));
+        assert_eq!(link.get(TokenKind::LinkMarkers), Some("[↓][↘]"));
+        assert_eq!(document.to_string(), text.replace("1000 1000", "1500 1000").replace("起点之果。\r\n", "起点之果。\r\n效果分类：0x1\r\n"));
+        assert_eq!(document.to_cards()[0].attack, 1500);
+        // Headers without a code get one after the name.
+        let link = document.cards_mut().nth(1).unwrap();
+        link.set_code(10001);
+        assert_eq!(link.header().text(), "日食爆龙(10001) 暗 LINK-2 龙/连接 1600 [↓][↘]");
+        assert_eq!(link.get(TokenKind::Code), Some("10001"));
+        let mut card = document.cards().next().unwrap().to_card().unwrap();
+        card.desc = "起点之果。\n终点之果。".to_string();
+        let first = document.card_mut(10000).unwrap();
+        first.replace(&card);
+        assert_eq!(first.to_string(), "[SD1-JP001] 原质阿尔法(10000) 暗 3星 恶魔/通常/调整 1500 1000 (OCG)\r\n起点之果。\r\n终点之果。\r\n效果分类：魔陷破坏\r\n");
+    }
+}
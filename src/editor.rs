@@ -0,0 +1,180 @@
+//! An xyyz document that stays parsed while it is being edited.
+//!
+//! Offsets are char offsets, like the `Range` of every card. JavaScript editors count UTF-16 code units,
+//! so `edit_utf16` and `span` convert from and to them.
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::card::{Card, CardTransformer, Range};
+use crate::cst::Document;
+use crate::spans::{OffsetTable, TextSpan};
+use crate::transformers::Xyyz;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct EditorDocument {
+    text: String,
+    cards: Vec<Card>
+}
+
+fn byte_offset(text: &str, offset: usize) -> usize {
+    text.char_indices().nth(offset).map_or(text.len(), |(i, _)| i)
+}
+
+fn range(card: &Card) -> Range {
+    card.range.unwrap_or((0..0).into())
+}
+
+/// Moves a card by `chars` chars and `lines` lines.
+fn shift(card: &mut Card, chars: isize, lines: isize) {
+    if let Some(range) = card.range.as_mut() {
+        range.start = range.start.saturating_add_signed(chars);
+        range.end = range.end.saturating_add_signed(chars);
+    }
+    for range in card.desc_ranges.iter_mut() {
+        range.start = range.start.saturating_add_signed(chars);
+        range.end = range.end.saturating_add_signed(chars);
+    }
+    if let Some(span) = card.provenance.span.as_mut() {
+        span.start_line = span.start_line.saturating_add_signed(lines);
+        span.end_line = span.end_line.saturating_add_signed(lines);
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl EditorDocument {
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(text: &str) -> EditorDocument {
+        EditorDocument { text: text.to_string(), cards: Xyyz::from_string(text) }
+    }
+
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    pub fn cards(&self) -> Vec<Card> {
+        self.cards.clone()
+    }
+
+    /// Replaces the chars `start..end` with `text` and returns the cards parsed again because of it.
+    ///
+    /// Only the cards around the edit are parsed again: the one the edit starts in, the one before it,
+    /// which takes over the lines of a card whose header is broken, and the first one after the edit,
+    /// which owns the comments before its header.
+    pub fn edit(&mut self, start: usize, end: usize, text: &str) -> Vec<Card> {
+        let length = self.text.chars().count();
+        let start = start.min(length);
+        let end = end.clamp(start, length);
+        let first = self.cards.partition_point(|c| range(c).start <= start).saturating_sub(2);
+        let after = self.cards.partition_point(|c| range(c).start <= end);
+        let last = (after + 1).min(self.cards.len());
+        let region_start = if first > 0 { range(&self.cards[first - 1]).end } else { 0 };
+        let region_end = if last < self.cards.len() { range(&self.cards[last - 1]).end } else { length };
+
+        let (start_byte, end_byte) = (byte_offset(&self.text, start), byte_offset(&self.text, end));
+        let chars = text.chars().count() as isize - (end - start) as isize;
+        let lines = text.matches('\n').count() as isize - self.text[start_byte..end_byte].matches('\n').count() as isize;
+        self.text.replace_range(start_byte..end_byte, text);
+
+        let region_end = region_end.saturating_add_signed(chars);
+        let region_start_byte = byte_offset(&self.text, region_start);
+        let region = &self.text[region_start_byte..byte_offset(&self.text, region_end)];
+        let lines_before = self.text[..region_start_byte].matches('\n').count() as isize;
+        let mut cards = Xyyz::from_string(region);
+        for card in cards.iter_mut() {
+            shift(card, region_start as isize, lines_before);
+        }
+        for card in self.cards[last..].iter_mut() {
+            shift(card, chars, lines);
+        }
+        self.cards.splice(first..last, cards.iter().cloned());
+        cards
+    }
+
+    /// `edit` with `start..end` in UTF-16 code units.
+    pub fn edit_utf16(&mut self, start: usize, end: usize, text: &str) -> Vec<Card> {
+        let span = OffsetTable::new(&self.text).from_utf16(start, end);
+        self.edit(span.chars.start, span.chars.end, text)
+    }
+
+    /// The chars `start..end` of the document, counted in bytes and UTF-16 code units too.
+    pub fn span(&self, start: usize, end: usize) -> TextSpan {
+        let length = self.text.chars().count();
+        OffsetTable::new(&self.text).from_chars(start.min(length), end.clamp(start.min(length), length))
+    }
+
+    /// Rewrites the text of the card with `code` from `card`, leaving the rest of the document alone.
+    ///
+    /// Returns the cards parsed again, which is empty if there is no card with `code`.
+    pub fn replace_card(&mut self, code: u32, card: &Card) -> Vec<Card> {
+        let Some(range) = self.cards.iter().find(|c| c.code == code).map(range) else { return Vec::new() };
+        // The line ending after the card goes along, so the new lines end like the others.
+        let start = byte_offset(&self.text, range.start);
+        let end = byte_offset(&self.text, range.end);
+        let ending = ["\r\n", "\n"].into_iter().find(|e| self.text[end..].starts_with(e)).unwrap_or_default();
+        let mut document = Document::parse(&self.text[start..end + ending.len()]);
+        let Some(node) = document.cards_mut().next() else { return Vec::new() };
+        node.replace(card);
+        self.edit(range.start, range.end + ending.len(), &document.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::editor::*;
+
+    fn assert_synced(document: &EditorDocument) {
+        let expected = Xyyz::from_string(&document.text);
+        assert_eq!(document.cards.len(), expected.len());
+        for (card, expected) in document.cards.iter().zip(expected.iter()) {
+            assert_eq!(Xyyz::to_string(card), Xyyz::to_string(expected));
+            assert_eq!(card.range, expected.range);
+            assert_eq!(card.desc_ranges, expected.desc_ranges);
+            assert_eq!(card.provenance.span.map(|s| s.start_line), expected.provenance.span.map(|s| s.start_line));
+        }
+    }
+
+    #[test]
+    fn test_edit() {
+        let text = include_str!("transformers/test_data/xyyz.txt");
+        let mut document = EditorDocument::new(text);
+        let count = document.cards.len();
+        let offset = text[..text.find("1000 1000").unwrap()].chars().count();
+        let changed = document.edit(offset, offset + 4, "1500");
+        assert!(changed.iter().any(|c| c.attack == 1500));
+        assert!(changed.len() < count);
+        assert_synced(&document);
+
+        // Breaking a header merges its card into the one before.
+        let offset = document.text.chars().position(|c| c == '(').unwrap();
+        document.edit(offset, offset + 1, "");
+        assert_synced(&document);
+        document.edit(offset, offset, "(");
+        assert_synced(&document);
+
+        let offset = document.text.chars().count();
+        document.edit(offset, offset, "\n\n原质贝塔(10001) 通常魔法\r\n新的卡。");
+        assert_synced(&document);
+        assert_eq!(document.cards.len(), count + 1);
+
+        let mut card = document.cards.iter().find(|c| c.code == 114514269).unwrap().clone();
+        card.desc = "起点之果。\n终点之果。".to_string();
+        assert_eq!(document.replace_card(114514269, &card).iter().filter(|c| c.code == 114514269).count(), 1);
+        assert!(document.text.contains("起点之果。\n终点之果。"));
+        assert_synced(&document);
+        assert!(document.replace_card(1, &card).is_empty());
+        // CRLF documents get CRLF lines.
+        let mut document = EditorDocument::new("原质阿尔法(10000) 通常魔法\r\n起点之果。\r\n\r\n原质贝塔(10001) 通常魔法");
+        let mut card = document.cards[0].clone();
+        card.desc = "起点之果。\n终点之果。".to_string();
+        document.replace_card(10000, &card);
+        assert_eq!(document.text, "原质阿尔法(10000) 通常魔法\r\n起点之果。\r\n终点之果。\r\n\r\n原质贝塔(10001) 通常魔法");
+        assert_synced(&document);
+
+        let mut document = EditorDocument::new("原质😀(1) 通常魔法\n\n原质贝塔(2) 通常魔法");
+        assert_eq!(document.span(3, 6).utf16, (4..7).into());
+        document.edit_utf16(4, 7, "(3)");
+        assert_eq!(document.cards[0].code, 3);
+        assert_synced(&document);
+    }
+}
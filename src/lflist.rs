@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use crate::card::{Card, Limit};
+
+/// `12345 1 --name`, one card of a forbidden/limited list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub code: u32,
+    pub count: u8,
+    pub comment: String,
+    /// Comment lines like `#forbidden` right before this entry.
+    pub comments: Vec<String>
+}
+
+/// One `!name` list of an lflist.conf.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Banlist {
+    pub name: String,
+    /// `$whitelist` lists forbid every card they don't name.
+    pub whitelist: bool,
+    pub entries: Vec<Entry>,
+    /// Comment lines right before the `!name` line, which only the first list of a file has.
+    pub comments: Vec<String>,
+    /// Comment lines after the last entry.
+    pub trailing_comments: Vec<String>
+}
+
+const COUNT_NAMES: [&str; 4] = ["禁止", "限制", "准限制", "无限制"];
+
+pub fn format_count(count: u8) -> &'static str {
+    COUNT_NAMES.get(count as usize).copied().unwrap_or(COUNT_NAMES[3])
+}
+
+/// `禁止`, `限制`, `准限制`, `无限制` or a number of copies.
+pub fn parse_count(text: &str) -> Option<u8> {
+    COUNT_NAMES.iter().position(|n| *n == text).map(|i| i as u8).or(text.parse().ok())
+}
+
+impl Banlist {
+    pub fn new(name: &str) -> Banlist {
+        Banlist { name: name.to_string(), whitelist: false, entries: Vec::new(), comments: Vec::new(), trailing_comments: Vec::new() }
+    }
+
+    /// Copies of `code` this list allows.
+    pub fn count(&self, code: u32) -> u8 {
+        match self.entries.iter().find(|e| e.code == code) {
+            Some(entry) => entry.count,
+            None if self.whitelist => 0,
+            None => 3
+        }
+    }
+
+    /// Makes the list allow `count` copies of `card`, adding or dropping its entry as needed.
+    pub fn set_count(&mut self, card: &Card, count: u8) {
+        let default = if self.whitelist { 0 } else { 3 };
+        match self.entries.iter().position(|e| e.code == card.code) {
+            Some(i) if count == default => {
+                // Comments like `#limit` stay for the entries after.
+                let mut comments = self.entries.remove(i).comments;
+                let after = match self.entries.get_mut(i) { Some(next) => &mut next.comments, None => &mut self.trailing_comments };
+                comments.append(after);
+                *after = comments;
+            }
+            Some(i) => self.entries[i].count = count,
+            None if count == default => {}
+            None => self.entries.push(Entry { code: card.code, count, comment: card.name.clone(), comments: Vec::new() })
+        }
+    }
+}
+
+/// Parses the lists of an lflist.conf, keeping comments with the entry after them.
+pub fn parse(text: &str) -> Vec<Banlist> {
+    let mut lists: Vec<Banlist> = Vec::new();
+    let mut comments = Vec::new();
+    for raw_line in text.split("\n") {
+        let line = raw_line.trim_end_matches('\r');
+        if let Some(name) = line.strip_prefix("!") {
+            let mut list = Banlist::new(name.trim());
+            match lists.last_mut() {
+                Some(last) => last.trailing_comments.append(&mut comments),
+                None => list.comments.append(&mut comments)
+            }
+            lists.push(list);
+            continue;
+        }
+        let Some(list) = lists.last_mut() else {
+            // `#[a][b]` before the first list only repeats the names of the lists, `write` puts it back.
+            let names = line.starts_with("#[") && line.ends_with("]");
+            if !names && !line.trim().is_empty() { comments.push(line.to_string()) }
+            continue
+        };
+        if line.trim() == "$whitelist" { list.whitelist = true; continue }
+        let mut words = line.splitn(3, char::is_whitespace);
+        match (words.next().and_then(|w| w.parse().ok()), words.next().and_then(|w| w.parse().ok())) {
+            (Some(code), Some(count)) if !line.starts_with("#") => {
+                let comment = words.next().unwrap_or_default().trim().trim_start_matches("--").trim().to_string();
+                list.entries.push(Entry { code, count, comment, comments: std::mem::take(&mut comments) });
+            }
+            _ if line.trim().is_empty() => {}
+            _ => comments.push(line.to_string())
+        }
+    }
+    if let Some(list) = lists.last_mut() { list.trailing_comments.append(&mut comments) }
+    lists
+}
+
+pub fn write(lists: &[Banlist]) -> String {
+    let mut text = lists.iter().map(|l| format!("[{}]", l.name)).fold("#".to_string(), |s, n| s + &n) + "\n";
+    for list in lists {
+        list.comments.iter().for_each(|c| { text += c; text += "\n" });
+        text += &format!("!{}\n", list.name);
+        if list.whitelist { text += "$whitelist\n" }
+        for entry in list.entries.iter() {
+            entry.comments.iter().for_each(|c| { text += c; text += "\n" });
+            text += &format!("{} {}", entry.code, entry.count);
+            if !entry.comment.is_empty() { text += &format!(" --{}", entry.comment) }
+            text += "\n";
+        }
+        list.trailing_comments.iter().for_each(|c| { text += c; text += "\n" });
+    }
+    text
+}
+
+/// Sets the limits of every card from `lists`.
+pub fn annotate(cards: &mut [Card], lists: &[Banlist]) {
+    for card in cards.iter_mut() {
+        card.limits = lists.iter()
+            .map(|l| Limit { list: l.name.clone(), count: l.count(card.code) })
+            .filter(|l| l.count < 3)
+            .collect();
+    }
+}
+
+/// Writes the limits of `cards` into `lists`, adding lists that only the cards name.
+///
+/// Only the lists a card names a limit in are changed for it, so cards without limits, like those of a cdb,
+/// keep their entries. `无限制` lifts a limit.
+pub fn apply_limits(lists: &mut Vec<Banlist>, cards: &[Card]) {
+    for limit in cards.iter().flat_map(|c| c.limits.iter()) {
+        if !lists.iter().any(|l| l.name == limit.list) { lists.push(Banlist::new(&limit.list)) }
+    }
+    let mut seen = HashSet::new();
+    for card in cards.iter().filter(|c| seen.insert(c.code)) {
+        for limit in card.limits.iter() {
+            if let Some(list) = lists.iter_mut().find(|l| l.name == limit.list) { list.set_count(card, limit.count) }
+        }
+    }
+}
+
+/// Entries of `lists` whose code is none of `cards`, as list names and codes.
+pub fn unknown_codes<'a>(lists: &'a [Banlist], cards: &[Card]) -> Vec<(&'a str, u32)> {
+    let codes = cards.iter().map(|c| c.code).collect::<HashSet<_>>();
+    lists.iter()
+        .flat_map(|l| l.entries.iter().filter(|e| !codes.contains(&e.code)).map(|e| (l.name.as_str(), e.code)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::lflist::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_lflist() {
+        let text = "#[2024.4 OCG][自制]\n!2024.4 OCG\n#forbidden\n100 0 --甲\n#limit\n101 1 --乙\n55144522 1 --强欲之壶\n!自制\n$whitelist\n100 3\n";
+        let mut lists = parse(text);
+        assert_eq!(write(&lists), text);
+        let mut cards = Xyyz::from_string("甲(100) 通常魔法\n\n乙(101) 通常魔法\n\n丙(102) 通常魔法");
+        annotate(&mut cards, &lists);
+        assert_eq!(cards[0].limits, vec![Limit { list: "2024.4 OCG".to_string(), count: 0 }]);
+        assert_eq!(cards[2].limits.iter().map(|l| l.list.as_str()).collect::<Vec<_>>(), vec!["自制"]);
+        assert_eq!(unknown_codes(&lists, &cards), vec![("2024.4 OCG", 55144522)]);
+        assert!(Xyyz::to_string(&cards[1]).ends_with("\n禁限：2024.4 OCG 限制、自制 禁止"));
+        // Cards without limits leave the lists alone.
+        let mut unchanged = parse(text);
+        apply_limits(&mut unchanged, &Xyyz::from_string("甲(100) 通常魔法\n\n乙(101) 通常魔法"));
+        assert_eq!(write(&unchanged), text);
+        let cards = Xyyz::from_string("甲(100) 通常魔法\n禁限：2024.4 OCG 无限制、自制 无限制\n\n乙(101) 通常魔法\n禁限：自制 2");
+        assert_eq!(cards[1].limits, vec![Limit { list: "自制".to_string(), count: 2 }]);
+        apply_limits(&mut lists, &cards);
+        assert_eq!(write(&lists), "#[2024.4 OCG][自制]\n!2024.4 OCG\n#forbidden\n#limit\n101 1 --乙\n55144522 1 --强欲之壶\n!自制\n$whitelist\n100 3\n101 2 --乙\n");
+        // A whitelist forbids the cards it doesn't name, both ways.
+        let mut annotated = Xyyz::from_string("丙(102) 通常魔法");
+        annotate(&mut annotated, &lists);
+        assert_eq!(annotated[0].limits, vec![Limit { list: "自制".to_string(), count: 0 }]);
+        apply_limits(&mut lists, &annotated);
+        assert_eq!(lists[1].count(102), 0);
+        assert_eq!(write(&lists).matches("102").count(), 0);
+        // Comments at the top of the file stay there.
+        let text = "#[2024.4 OCG]\n# 自制卡组用\n#@\n!2024.4 OCG\n100 0 --甲\n";
+        assert_eq!(parse(text)[0].comments, vec!["# 自制卡组用", "#@"]);
+        assert_eq!(write(&parse(text)), text);
+    }
+}
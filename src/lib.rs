@@ -1,6 +1,18 @@
+pub mod allocator;
+pub mod archetype;
 pub mod card;
 pub mod constants;
+pub mod cst;
+pub mod editor;
+pub mod lflist;
+pub mod lint;
+pub mod packs;
+pub mod pool;
+pub mod references;
+pub mod renumber;
+pub mod spans;
 pub mod transformers;
+pub mod ydk;
 
 #[cfg(target_arch="wasm32")]
 use std::ops::Deref;
@@ -13,6 +25,12 @@ pub fn parse(text: &str) -> Vec<card::Card> {
     <transformers::Xyyz as card::CardTransformer>::from_string(text)
 }
 
+/// Spans of every card and its fields, in bytes, chars and UTF-16 code units.
+#[cfg_attr(target_arch="wasm32", wasm_bindgen)]
+pub fn card_spans(text: &str) -> Vec<spans::CardSpans> {
+    spans::card_spans(text)
+}
+
 #[cfg_attr(target_arch="wasm32", wasm_bindgen)]
 pub fn set_string_conf(text: &str) {
     transformers::set_string_conf(text);
@@ -24,12 +42,12 @@ pub fn format(card: &card::Card) -> String {
 }
 
 #[cfg_attr(target_arch="wasm32", wasm_bindgen)]
-pub fn format_subtype(_type: u32) -> String {
+pub fn format_subtype(_type: u64) -> String {
     transformers::Xyyz::format_subtype(&constants::Type::from_bits_retain(_type))
 }
 
 #[cfg_attr(target_arch="wasm32", wasm_bindgen)]
-pub fn format_race(race: u32) -> String {
+pub fn format_race(race: u64) -> String {
     transformers::Xyyz::format_race(&constants::Race::from_bits_retain(race))
 }
 
@@ -85,15 +103,15 @@ extern "C" {
 
 #[cfg(target_arch="wasm32")]
 #[wasm_bindgen]
-pub fn parse_database(db: &Database) -> Vec<card::Card> {
-    transformers::CDB::from_database(db)
+pub fn parse_database(db: &Database, dialect: constants::Dialect) -> Vec<card::Card> {
+    transformers::CDB::from_database(db, dialect)
 }
 
 #[cfg(target_arch="wasm32")]
 #[wasm_bindgen]
-pub fn write_database(db: &Database, text: &str) {
+pub fn write_database(db: &Database, text: &str, dialect: constants::Dialect) {
     let cards = parse(text);
-    transformers::CDB::write_database(&cards, db)
+    transformers::CDB::write_database(&cards, db, dialect)
 }
 
 
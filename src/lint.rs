@@ -0,0 +1,76 @@
+mod glossary;
+mod references;
+mod structure;
+mod text;
+
+pub use glossary::*;
+pub use references::*;
+pub use structure::*;
+pub use text::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::{Card, Provenance, Range};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub rule: String,
+    pub severity: Severity,
+    pub code: u32,
+    pub name: String,
+    pub message: String,
+    pub range: Option<Range>,
+    pub provenance: Provenance
+}
+
+impl Diagnostic {
+    pub fn new(rule: &str, severity: Severity, card: &Card, message: String) -> Diagnostic {
+        Diagnostic {
+            rule: rule.to_string(),
+            severity,
+            code: card.code,
+            name: card.name.clone(),
+            message,
+            range: card.range,
+            provenance: card.provenance.clone()
+        }
+    }
+
+    /// A diagnostic at chars `columns` of line `line` of the effect text, counted from 1.
+    ///
+    /// Cards not read from an xyyz source don't know where their lines are, so the diagnostic covers the card.
+    pub fn at_line(rule: &str, severity: Severity, card: &Card, line: usize, columns: std::ops::Range<usize>, message: String) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(rule, severity, card, message);
+        if let Some(range) = card.desc_ranges.get(line - 1) {
+            diagnostic.range = Some((range.start + columns.start..range.start + columns.end).into());
+        }
+        diagnostic
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LintOptions {
+    /// Rule ids to suppress.
+    pub allow: Vec<String>,
+    /// Cards of an official database that 「name」 references may point to.
+    pub official: Vec<Card>,
+    pub glossary: Glossary
+}
+
+/// Runs every rule over the cards, dropping diagnostics whose rule id is allowed.
+pub fn lint(cards: &[Card], options: &LintOptions) -> Vec<Diagnostic> {
+    lint_structure(cards)
+        .into_iter()
+        .chain(lint_text(cards))
+        .chain(lint_references(cards, &options.official))
+        .chain(lint_glossary(cards, &options.glossary))
+        .filter(|d| !options.allow.contains(&d.rule))
+        .collect()
+}
@@ -0,0 +1,129 @@
+use std::ops::Range;
+
+use crate::card::Card;
+use crate::cst::{Document, LineKind, TokenKind};
+use crate::lint::{Diagnostic, Severity};
+
+pub const GLOSSARY: &str = "glossary";
+
+/// Banned terms and their preferred replacements.
+///
+/// The file has one `banned preferred` pair per line, `#` starts a comment line.
+#[derive(Debug, Default, Clone)]
+pub struct Glossary {
+    pub terms: Vec<(String, String)>
+}
+
+impl Glossary {
+    pub fn parse(text: &str) -> Glossary {
+        let mut terms = Vec::new();
+        for line in text.split("\n").map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with("#") { continue; }
+            let mut parts = line.split_whitespace();
+            if let (Some(banned), Some(preferred)) = (parts.next(), parts.next()) {
+                terms.push((banned.to_string(), preferred.to_string()));
+            } else {
+                eprintln!("Glossary line '{}' has no preferred term.", line);
+            }
+        }
+        Glossary { terms }
+    }
+
+    /// Banned terms of `text` with their char ranges. Card names quoted in 「」 are left out,
+    /// and a banned term inside its own preferred term does not count.
+    pub fn find(&self, text: &str) -> Vec<(Range<usize>, &(String, String))> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut quoted = vec![false; chars.len()];
+        let mut depth = 0;
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '「' { depth += 1 }
+            quoted[i] = depth > 0;
+            if *c == '」' && depth > 0 { depth -= 1 }
+        }
+        let matches = |term: &str| text.match_indices(term)
+            .map(|(i, m)| { let start = text[..i].chars().count(); start..start + m.chars().count() })
+            .collect::<Vec<_>>();
+        let mut found = Vec::new();
+        for term in self.terms.iter() {
+            let preferred = matches(&term.1);
+            for range in matches(&term.0) {
+                if quoted[range.clone()].iter().any(|q| *q) { continue }
+                if preferred.iter().any(|p| p.start <= range.start && range.end <= p.end) { continue }
+                found.push((range, term));
+            }
+        }
+        found.sort_by_key(|(range, _)| range.start);
+        found
+    }
+
+    /// Replaces the banned terms of `text`, see `find`.
+    pub fn fix(&self, text: &str) -> String {
+        let mut chars = text.chars().collect::<Vec<_>>();
+        let mut end = usize::MAX;
+        // Backwards, so that earlier ranges stay where they are. Overlapping terms give way to the earlier one.
+        for (range, (_, preferred)) in self.find(text).into_iter().rev() {
+            if range.end > end { continue }
+            end = range.start;
+            chars.splice(range, preferred.chars());
+        }
+        chars.into_iter().collect()
+    }
+
+    /// Rewrites the effect text and 提示文本 lines of the cards inside an xyyz source, leaving other lines as they are.
+    pub fn fix_source(&self, source: &str) -> String {
+        let mut document = Document::parse(source);
+        for line in document.cards_mut().flat_map(|card| card.lines.iter_mut()) {
+            let token = match line.kind {
+                LineKind::Description => line.token_mut(TokenKind::Text),
+                LineKind::Metadata if line.key() == Some(HINT_PREFIX) => line.token_mut(TokenKind::Value),
+                _ => None
+            };
+            if let Some(token) = token { token.text = self.fix(&token.text) }
+        }
+        document.to_string()
+    }
+}
+
+const HINT_PREFIX: &str = "提示文本：";
+
+pub fn lint_glossary(cards: &[Card], glossary: &Glossary) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let message = |(banned, preferred): &(String, String)| format!("Use 「{}」 instead of 「{}」.", preferred, banned);
+    for card in cards {
+        for (n, line) in card.desc.split("\n").enumerate() {
+            for (range, term) in glossary.find(line) {
+                diagnostics.push(Diagnostic::at_line(GLOSSARY, Severity::Warning, card, n + 1, range, message(term)));
+            }
+        }
+        for text in card.texts.iter() {
+            for (_, term) in glossary.find(text) {
+                diagnostics.push(Diagnostic::new(GLOSSARY, Severity::Warning, card, message(term)));
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::lint::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_glossary() {
+        let glossary = Glossary::parse("# 缩写\n特召 特殊召唤\n魔陷 魔法·陷阱");
+        let source = "特召龙(10000) 暗 4星 龙 1600 1000\n①：这张卡特召成功时，通常召唤的怪兽。\n提示文本：特召\n\n#特召\n";
+        let cards = Xyyz::from_string(source);
+        let diagnostics = lint_glossary(&cards, &glossary);
+        assert_eq!(diagnostics.len(), 2);
+        let start = cards[0].desc_ranges[0].start + 5;
+        assert_eq!(diagnostics[0].range, Some((start..start + 2).into()));
+        assert_eq!(glossary.fix_source(source), "特召龙(10000) 暗 4星 龙 1600 1000\n①：这张卡特殊召唤成功时，通常召唤的怪兽。\n提示文本：特殊召唤\n\n#特召\n");
+        // Card names in 「」 and metadata lines stay as they are.
+        let source = "魔陷龙(10001) 通常魔法\n把「特召龙」从魔陷区域加入手卡。\n卡包：[魔陷-JP001]\n禁限：特召 1";
+        let cards = Xyyz::from_string(source);
+        assert_eq!(lint_glossary(&cards, &glossary).len(), 1);
+        assert_eq!(glossary.fix_source(source), source.replace("从魔陷区域", "从魔法·陷阱区域"));
+    }
+}
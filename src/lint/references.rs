@@ -0,0 +1,21 @@
+use crate::card::Card;
+use crate::lint::{Diagnostic, Severity};
+use crate::references::ReferenceIndex;
+
+pub const UNRESOLVED_REFERENCE: &str = "unresolved-reference";
+pub const AMBIGUOUS_REFERENCE: &str = "ambiguous-reference";
+
+pub fn lint_references(cards: &[Card], official: &[Card]) -> Vec<Diagnostic> {
+    let index = ReferenceIndex::build(cards, official);
+    let mut diagnostics = Vec::new();
+    for (reference, card) in index.references.iter().map(|r| (r, &cards[r.card])) {
+        if !reference.is_resolved() {
+            diagnostics.push(Diagnostic::new(UNRESOLVED_REFERENCE, Severity::Warning, card,
+                format!("「{}」 is neither a known card nor an archetype.", reference.name)));
+        } else if reference.is_ambiguous() {
+            diagnostics.push(Diagnostic::new(AMBIGUOUS_REFERENCE, Severity::Warning, card,
+                format!("「{}」 matches cards {:?}.", reference.name, reference.cards)));
+        }
+    }
+    diagnostics
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::card::Card;
+use crate::constants::*;
+use crate::lint::{Diagnostic, Severity};
+use crate::transformers::SET_NAMES;
+
+pub const LINK_LEVEL: &str = "link-level";
+pub const XYZ_RANK: &str = "xyz-rank";
+pub const PENDULUM_SCALE: &str = "pendulum-scale";
+pub const SPELL_STATS: &str = "spell-stats";
+pub const MISSING_ALIAS: &str = "missing-alias";
+pub const UNKNOWN_SETCODE: &str = "unknown-setcode";
+pub const SETCODE_COUNT: &str = "setcode-count";
+pub const DUPLICATE_CODE: &str = "duplicate-code";
+
+pub fn lint_structure(cards: &[Card]) -> Vec<Diagnostic> {
+    let setnames = SET_NAMES.load();
+    let codes = cards.iter().map(|c| c.code).collect::<HashSet<_>>();
+    let mut seen = HashMap::<u32, &Card>::new();
+    let mut diagnostics = Vec::new();
+    for card in cards {
+        if card._type.contains(Type::Link) {
+            let markers = card.link_marker.iter().count();
+            if card.level as usize != markers {
+                diagnostics.push(Diagnostic::new(LINK_LEVEL, Severity::Error, card,
+                    format!("Link rating {} does not match {} link markers.", card.level, markers)));
+            }
+        }
+        if card._type.contains(Type::Xyz) && card.level == 0 {
+            diagnostics.push(Diagnostic::new(XYZ_RANK, Severity::Error, card, "Xyz monster has rank 0.".to_string()));
+        }
+        if card._type.contains(Type::Pendulum) && card.lscale == 0 && card.rscale == 0 {
+            diagnostics.push(Diagnostic::new(PENDULUM_SCALE, Severity::Warning, card, "Pendulum card has no scales.".to_string()));
+        }
+        if !card._type.contains(Type::Monster) &&
+            (card.attack != 0 || card.defense != 0 || card.level != 0 || !card.race.is_empty() || !card.attribute.is_empty()) {
+            diagnostics.push(Diagnostic::new(SPELL_STATS, Severity::Error, card, "Spell or trap card has monster stats.".to_string()));
+        }
+        if card.alias > 0 && !codes.contains(&card.alias) {
+            diagnostics.push(Diagnostic::new(MISSING_ALIAS, Severity::Error, card,
+                format!("Alias {} is not a loaded card.", card.alias)));
+        }
+        let setcodes = card.setcodes();
+        // Without strings.conf every archetype would be unknown.
+        if !setnames.is_empty() {
+            for setcode in setcodes.iter().filter(|s| !setnames.contains_key(s)) {
+                diagnostics.push(Diagnostic::new(UNKNOWN_SETCODE, Severity::Warning, card,
+                    format!("Archetype 0x{:X} is not in strings.conf.", setcode)));
+            }
+        }
+        if setcodes.len() > 4 {
+            diagnostics.push(Diagnostic::new(SETCODE_COUNT, Severity::Error, card,
+                format!("{} archetypes, ygopro keeps only 4.", setcodes.len())));
+        }
+        // Cards without a code yet are not duplicates of each other.
+        match seen.get(&card.code).filter(|_| card.code > 0) {
+            Some(first) => diagnostics.push(Diagnostic::new(DUPLICATE_CODE, Severity::Error, card,
+                format!("Code {} is also used by {} at {}.", card.code, first.name, first.provenance))),
+            None => { seen.insert(card.code, card); }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::lint::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_lint_structure() {
+        let cards = Xyyz::from_string("
+链接怪兽(10000) 暗 LINK-2 龙/连接 1600 [↓]
+
+超量怪兽(10001) 暗 0阶 龙/超量 1600 1000
+
+灵摆怪兽(10002) 暗 4星 龙/灵摆 1600 1000
+
+别名卡(10002=>20000) 通常魔法
+系列：0x1、0x2、0x3、0x4、0x5
+
+未编号魔法 通常魔法
+
+未编号陷阱 通常陷阱
+");
+        let rules = lint_structure(&cards).into_iter().map(|d| d.rule).collect::<Vec<_>>();
+        assert_eq!(rules, vec![LINK_LEVEL, XYZ_RANK, PENDULUM_SCALE, MISSING_ALIAS, SETCODE_COUNT, DUPLICATE_CODE]);
+        let options = LintOptions { allow: vec![XYZ_RANK.to_string()], ..Default::default() };
+        assert!(lint(&cards, &options).iter().all(|d| d.rule != XYZ_RANK));
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::card::Card;
+use crate::constants::Type;
+use crate::lint::{Diagnostic, Severity};
+use crate::transformers::PENDULUM_REGEX;
+
+pub const EFFECT_NUMBER: &str = "effect-number";
+pub const BRACKET_BALANCE: &str = "bracket-balance";
+pub const HALFWIDTH_PUNCTUATION: &str = "halfwidth-punctuation";
+pub const TRAILING_WHITESPACE: &str = "trailing-whitespace";
+pub const PENDULUM_LAYOUT: &str = "pendulum-layout";
+
+pub const CIRCLED_NUMBERS: &str = "①②③④⑤⑥⑦⑧⑨⑩⑪⑫⑬⑭⑮⑯⑰⑱⑲⑳";
+const HALFWIDTH_PUNCTUATIONS: &str = ",:;!?()";
+const PENDULUM_SEPARATOR: &str = "【怪兽效果】";
+
+static EFFECT_LABEL_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"([①-⑳])[：:]").unwrap() });
+
+pub fn lint_text(cards: &[Card]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for card in cards {
+        let mut expected = 0;
+        let mut depth = 0;
+        for (n, line) in card.desc.split("\n").enumerate() {
+            let n = n + 1;
+            let column = |byte: usize| line[..byte].chars().count();
+            // Pendulum and monster effects are numbered separately.
+            if line == PENDULUM_SEPARATOR { expected = 0; }
+            for label in EFFECT_LABEL_REGEX.captures_iter(line) {
+                let number = CIRCLED_NUMBERS.chars().position(|c| label[1].starts_with(c)).unwrap();
+                if number != expected {
+                    let m = label.get(1).unwrap();
+                    diagnostics.push(Diagnostic::at_line(EFFECT_NUMBER, Severity::Warning, card, n, column(m.start())..column(m.end()),
+                        format!("Line {}: expected {}, found {}.", n, CIRCLED_NUMBERS.chars().nth(expected).unwrap_or('?'), &label[1])));
+                }
+                expected = number + 1;
+            }
+            for (i, c) in line.chars().enumerate() {
+                if c == '「' { depth += 1; }
+                if c == '」' { depth -= 1; }
+                if depth < 0 {
+                    diagnostics.push(Diagnostic::at_line(BRACKET_BALANCE, Severity::Error, card, n, i..i + 1, format!("Line {}: 」 without 「.", n)));
+                    depth = 0;
+                }
+            }
+            for (i, c) in line.chars().enumerate().filter(|(_, c)| HALFWIDTH_PUNCTUATIONS.contains(*c)) {
+                diagnostics.push(Diagnostic::at_line(HALFWIDTH_PUNCTUATION, Severity::Warning, card, n, i..i + 1,
+                    format!("Line {}: half-width '{}' in effect text.", n, c)));
+            }
+            if line.ends_with(char::is_whitespace) {
+                let length = line.chars().count();
+                let trailing = length - line.trim_end().chars().count();
+                diagnostics.push(Diagnostic::at_line(TRAILING_WHITESPACE, Severity::Warning, card, n, length - trailing..length, format!("Line {}: trailing whitespace.", n)));
+            }
+        }
+        if depth > 0 {
+            diagnostics.push(Diagnostic::new(BRACKET_BALANCE, Severity::Error, card, format!("{} 「 without 」.", depth)));
+        }
+        if card._type.contains(Type::Pendulum) && card._type.contains(Type::Monster) {
+            let lines = card.desc.split("\n").collect::<Vec<_>>();
+            if !lines.first().is_some_and(|l| PENDULUM_REGEX.is_match(l)) {
+                let length = lines.first().map_or(0, |l| l.chars().count());
+                diagnostics.push(Diagnostic::at_line(PENDULUM_LAYOUT, Severity::Error, card, 1, 0..length, "Pendulum text does not start with ←L 【灵摆】 R→.".to_string()));
+            }
+            if !lines.contains(&PENDULUM_SEPARATOR) {
+                diagnostics.push(Diagnostic::new(PENDULUM_LAYOUT, Severity::Error, card, format!("Pendulum text has no {} line.", PENDULUM_SEPARATOR)));
+            }
+        }
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::lint::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_lint_text() {
+        let cards = Xyyz::from_string("
+灵摆怪兽(10000) 暗 4星 龙/灵摆 1600 1000
+①：自己场上的「龙」怪兽攻击力上升100。
+【怪兽效果】
+①：这张卡召唤成功时发动。
+③：以场上1只怪兽为对象才能发动(那只怪兽破坏)。\u{3000}
+");
+        let diagnostics = lint_text(&cards);
+        let rules = diagnostics.iter().map(|d| d.rule.as_str()).collect::<Vec<_>>();
+        assert_eq!(rules, vec![EFFECT_NUMBER, HALFWIDTH_PUNCTUATION, HALFWIDTH_PUNCTUATION, TRAILING_WHITESPACE, PENDULUM_LAYOUT]);
+        let line_start = cards[0].desc_ranges[3].start;
+        assert_eq!(diagnostics[0].range.map(|r| (r.start - line_start, r.end - line_start)), Some((0, 1)));
+        assert_eq!(diagnostics[2].range.map(|r| r.start - line_start), Some(23));
+    }
+}
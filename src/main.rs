@@ -1,18 +1,32 @@
+mod allocator;
+mod archetype;
 mod card;
 mod constants;
+// The binary rewrites sources through a part of the tree only.
+#[allow(dead_code)]
+mod cst;
+mod lflist;
+mod lint;
+mod packs;
+mod pool;
+mod references;
+mod renumber;
 mod transformers;
+mod ydk;
 
 use std::fs::write;
 
-use card::CardTransformer;
-use clap::Parser;
-use constants::{Format, OT};
+use card::{Card, CardTransformer};
+use clap::{CommandFactory, Parser, Subcommand};
+use constants::{Dialect, Format, HeaderStyle, Region, OT};
 use transformers::*;
 
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Format of source files.
     /// 
     /// .txt file is always xyyz.
@@ -20,39 +34,192 @@ struct Args {
     /// .lua file is always script.
     /// .cdb file is always cdb.
     /// If none of file appendix matches, program will use value of this field.
-    #[arg(long)]
+    #[arg(long, global=true)]
     from_format: Option<Format>,
     /// Source files.
     /// 
     /// If no value given, source is stdin, format is xyyz.
-    #[arg(short, long)]
+    #[arg(short, long, global=true)]
     from: Vec<String>,
+    /// Database dialect of cdb and sql sources.
+    /// 
+    /// EDOPro stores a scope in `ot`, see `--to-dialect` for what each dialect keeps.
+    #[arg(long, global=true, value_enum, default_value_t=Dialect::Ygopro)]
+    from_dialect: Dialect,
     /// Format of target files.
     /// 
     /// Program will guess from filename if not provided. Default format is xyyz.
-    #[arg(long, default_value_t=Format::Xyyz)]
+    #[arg(long, global=true, default_value_t=Format::Xyyz)]
     to_format: Format,
     /// Target file. 
     /// 
     /// You can use {id} to set target lua name when target format is script.
     /// If no value given, target is stdout, format is xyyz.
-    #[arg(short, long, default_value="/dev/stdout")]
+    #[arg(short, long, global=true, default_value="/dev/stdout")]
     to: String,
+    /// Database dialect of cdb and sql targets.
+    /// 
+    /// Ygopro keeps only the OCG, TCG, Custom, SC and Draft flags of OT, EDOPro maps the others onto its scope.
+    /// Cdbs of both dialects hold at most 4 archetypes per card, the extra ones are dropped with a warning.
+    #[arg(long, global=true, value_enum, default_value_t=Dialect::Ygopro)]
+    to_dialect: Dialect,
     /// strings.conf path. 
     /// 
     /// These files is used to format set name inner xyyz format.
-    #[arg(long)]
+    #[arg(long, global=true)]
     strings: Vec<String>,
     /// If set, program will allow draft cards goto result
     /// 
     /// Draft card is an extra OT value only available in xyyz format.
-    #[arg(long)]
+    #[arg(long, global=true)]
     allow_draft: bool,
+    /// An xyyz file whose card order, comments and section headers are kept when writing xyyz.
+    #[arg(long, global=true)]
+    layout: Option<String>,
+    /// How headers are written when the target is xyyz.
+    ///
+    /// `original` writes the pack ID, rarity and Japanese name of cards that come with them, and no code.
+    #[arg(long, global=true, value_enum, default_value_t=HeaderStyle::Code)]
+    header_style: HeaderStyle,
+    /// lflist.conf files whose limits are shown in xyyz output.
+    /// 
+    /// Codes of the lists that are not among the loaded cards are reported.
+    #[arg(long, global=true)]
+    lflist: Vec<String>,
     /// Max line characters for script mode. 
-    #[arg(long, default_value_t=100)]
+    #[arg(long, global=true, default_value_t=100)]
     max_line_length: usize
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Check cards for structural errors and text conventions.
+    /// 
+    /// Prints one JSON diagnostic per line, and exits with 1 if any of them is an error.
+    Lint {
+        /// Rule ids to suppress, like duplicate-code.
+        #[arg(long)]
+        allow: Vec<String>,
+        /// Official databases that 「name」 references may point to.
+        #[arg(long)]
+        reference: Vec<String>,
+        /// Glossary of banned and preferred terms, one `banned preferred` pair per line.
+        #[arg(long)]
+        glossary: Option<String>,
+        /// Replace banned terms inside the xyyz sources before checking.
+        #[arg(long)]
+        fix: bool
+    },
+    /// List 「name」 references in effect texts and what they resolve to.
+    /// 
+    /// Prints one JSON reference per line.
+    References {
+        /// Official databases that 「name」 references may point to.
+        #[arg(long)]
+        reference: Vec<String>,
+        /// Only print codes of the cards that mention this card.
+        #[arg(long)]
+        mentions: Option<u32>
+    },
+    /// Give cards without a code an unused one, and write it back into the xyyz sources.
+    /// 
+    /// Codes of every source and reference database count as used.
+    Allocate {
+        /// Codes to allocate from, like 100000000-100099999.
+        #[arg(long, value_parser=allocator::parse_range)]
+        range: std::ops::RangeInclusive<u32>,
+        /// Official databases whose codes must not be reused.
+        #[arg(long)]
+        reference: Vec<String>
+    },
+    /// Move cards to new codes, in the sources, their scripts and decks.
+    /// 
+    /// xyyz sources keep their layout, only the codes and aliases of header lines change.
+    Renumber {
+        /// File of `old new` code pairs, one per line.
+        #[arg(long)]
+        mapping: Option<String>,
+        /// Codes to move as a whole, like 100000000-100000999.
+        #[arg(long, value_parser=allocator::parse_range, requires="base")]
+        block: Option<std::ops::RangeInclusive<u32>>,
+        /// The code that the start of --block moves to.
+        #[arg(long)]
+        base: Option<u32>,
+        /// Script path with {id}, like script/c{id}.lua.
+        #[arg(long)]
+        script: Option<String>,
+        /// ydk decks to rewrite.
+        #[arg(long)]
+        deck: Vec<String>
+    },
+    /// Change the setcode and/or name of an archetype, in the sources and the strings.conf files.
+    /// 
+    /// Sub-archetypes of a base archetype move along with its setcode.
+    Archetype {
+        /// Archetype to change, by name or like 0x1a.
+        #[arg(long)]
+        set: String,
+        /// New setcode, like 0x1b.
+        #[arg(long, value_parser=archetype::parse_setcode)]
+        setcode: Option<u16>,
+        /// New name.
+        #[arg(long)]
+        name: Option<String>
+    },
+    /// Rewrite xyyz sources in canonical form.
+    Fmt {
+        /// Don't write, exit with 1 if any source is not formatted.
+        #[arg(long)]
+        check: bool
+    },
+    /// Write one ydk per pack into the pack folder of a ygopro client, cards in set number order.
+    /// 
+    /// Packs are told apart by region, like SD1-JP and SD1-EN. A card is listed in the pack of each of its printings.
+    Packs {
+        /// Folder to write into, like ygopro/pack.
+        #[arg(long)]
+        dir: String
+    },
+    /// Keep the cards first released on or before a date, and write them like the default command.
+    /// 
+    /// Release dates come from the packs of the cards. Cards without one are left out.
+    Pool {
+        /// The last release date to keep, like 2024-04-27.
+        #[arg(long, value_parser=pool::parse_date)]
+        before: (u32, u32, u32),
+        /// Only count printings of this region, and keep only cards legal in it.
+        #[arg(long, value_enum)]
+        region: Option<Region>,
+        /// Also write the pool as an lflist.conf whitelist.
+        #[arg(long)]
+        lflist: Option<String>,
+        /// Also write the pool as a ydk.
+        #[arg(long)]
+        ydk: Option<String>,
+        /// Name of the list, defaults to the date and region.
+        #[arg(long)]
+        name: Option<String>
+    },
+    /// Write the limits of the cards, like the 禁限 lines of xyyz sources, into the --lflist lists.
+    /// 
+    /// Only the lists a card names are changed for it, 无限制 lifts a limit. Other entries stay as they are.
+    Lflist,
+    /// Check ydk decks against the cards: unknown codes, cards in the wrong deck, deck sizes and limits.
+    /// 
+    /// Limits come from the 禁限 lines of the sources and the --lflist lists. Exits with 1 if any deck has a problem.
+    Deck {
+        /// ydk decks to check.
+        #[arg(long)]
+        ydk: Vec<String>,
+        /// Write only the cards of the decks, like the default command, instead of failing on problems.
+        #[arg(long)]
+        extract: bool,
+        /// Write the name of each card into the ydk files, as a comment line before its code.
+        #[arg(long)]
+        annotate: bool
+    }
+}
+
 fn guess_format(path: &String, default: Option<Format>) -> Format {
     if path.ends_with(".cdb") { Format::CDB }
     else if path.ends_with(".sql") { Format::SQL }
@@ -60,20 +227,40 @@ fn guess_format(path: &String, default: Option<Format>) -> Format {
     else if path.ends_with(".xyyz") { Format::Xyyz }
     else if path.ends_with(".lua") {Format::Script }
     else if let Some(format) = default { format }
-    else { println!("Cannot determain the format by filename {}. Make it as xyyz...", path); Format::Xyyz }
+    else { eprintln!("Cannot determain the format by filename {}. Make it as xyyz...", path); Format::Xyyz }
+}
+
+/// Whether a flag of the command or its subcommands expects a value.
+fn takes_value(command: &clap::Command, flag: &str) -> bool {
+    if flag.contains('=') { return false }
+    std::iter::once(command).chain(command.get_subcommands())
+        .flat_map(|c| c.get_arguments())
+        .find(|a| a.get_long().map(|l| format!("--{}", l)).as_deref() == Some(flag) || a.get_short().map(|s| format!("-{}", s)).as_deref() == Some(flag))
+        .map(|a| a.get_action().takes_values())
+        .unwrap_or(true)
 }
 
 fn preprocess() -> Vec<String> {
+    let command = Args::command();
+    let subcommands = command.get_subcommands().map(|c| c.get_name().to_string()).collect::<Vec<_>>();
     let mut prcoessd_args = Vec::new();
     let mut controlling = String::new();
     let mut argument_count = 0;
     for arg in wild::args() {
         if arg.starts_with("-") {
-            controlling = arg.clone();
-            argument_count = 0;
+            if takes_value(&command, &arg) {
+                controlling = arg.clone();
+                argument_count = 0;
+            } else {
+                controlling = "--from".to_string();
+                argument_count = 1;
+            }
         } else {
             if prcoessd_args.len() == 0 {
                 controlling = "--from".to_string()
+            } else if prcoessd_args.len() == 1 && subcommands.contains(&arg) {
+                prcoessd_args.push(arg);
+                continue;
             }
             argument_count += 1;
             if argument_count > 1 {
@@ -85,33 +272,301 @@ fn preprocess() -> Vec<String> {
     prcoessd_args
 }
 
-fn main() {
-    let mut args = Args::parse_from(preprocess());
-    read_string_conf(&args.strings);
-    MAX_LINE_LENGTH.set(args.max_line_length).expect("MAX_LINE_LENGTH already inited.");
-    let mut cards = Vec::new();
-    if args.from.len() == 0 { args.from = vec!["/dev/stdin".to_string()] }
-    for source in args.from {
-        print!("Reading {}... ", source);
-        let card_parts = match guess_format(&source, args.from_format) {
-            Format::Xyyz => Xyyz::from_string(&std::fs::read_to_string(&source).expect(&format!("Read file {} failed", source))),
-            Format::SQL  =>  SQL::from_string(&std::fs::read_to_string(&source).expect(&format!("Read file {} failed", source))),
-            #[cfg(not(target_arch = "wasm32"))]
-            Format::CDB  =>  CDB::from_string(&source),
-            Format::Script => Script::from_string(&std::fs::read_to_string(&source).expect(&format!("Read file {} failed", source))),
-            _ => unimplemented!("Unimplemented type of source.")
+fn read_source(source: &String, args: &Args) -> Vec<Card> {
+    read_document(source, args).cards
+}
+
+/// The cards of a source, with the comments after the last one for xyyz sources.
+fn read_document(source: &String, args: &Args) -> XyyzDocument {
+    eprint!("Reading {}... ", source);
+    let mut document = match guess_format(source, args.from_format) {
+        Format::Xyyz => Xyyz::open_document(source),
+        Format::SQL  => XyyzDocument { cards: SQL::from_string_with(&std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source)), args.from_dialect), ..Default::default() },
+        #[cfg(not(target_arch = "wasm32"))]
+        Format::CDB  => XyyzDocument { cards: CDB::open(source, args.from_dialect), ..Default::default() },
+        Format::Script => XyyzDocument { cards: Script::from_string(&std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source))), ..Default::default() },
+        _ => unimplemented!("Unimplemented type of source.")
+    };
+    eprintln!("Parsed {} cards.", document.cards.len());
+    for card in document.cards.iter_mut().filter(|c| c.provenance.path.is_none()) {
+        card.provenance.path = Some(source.clone());
+    }
+    document
+}
+
+/// Source files with every xyyz file they include right after them, for commands that rewrite sources in place.
+fn expand_sources(args: &Args) -> Vec<String> {
+    let mut sources = Vec::new();
+    for source in args.from.iter() {
+        let files = match guess_format(source, args.from_format) {
+            Format::Xyyz => Xyyz::open(source).into_iter().filter_map(|c| c.provenance.path).collect(),
+            _ => vec![source.clone()]
         };
-        cards.extend(card_parts)
+        for file in std::iter::once(source.clone()).chain(files) {
+            if !sources.contains(&file) { sources.push(file) }
+        }
+    }
+    sources
+}
+
+/// Every card of the sources, Draft cards included, and the comments after the last card of the xyyz sources.
+fn read_every_card(args: &Args) -> XyyzDocument {
+    let mut document = XyyzDocument::default();
+    let sources = if args.from.len() == 0 { vec!["/dev/stdin".to_string()] } else { args.from.clone() };
+    for source in sources {
+        document.append(read_document(&source, args))
     };
-    if !(args.allow_draft) {
-        cards = cards.into_iter().filter(|c| !c.ot.contains(OT::Draft)).collect();
+    let mut seen = std::collections::HashMap::new();
+    for card in document.cards.iter().filter(|c| c.code > 0) {
+        if let Some(first) = seen.insert(card.code, &card.provenance) {
+            eprintln!("{}: card {} is also read from {}.", card.provenance, card.code, first);
+        }
+    }
+    document
+}
+
+fn read_cards(every_card: Vec<Card>, args: &Args) -> Vec<Card> {
+    if args.allow_draft { return every_card }
+    every_card.into_iter().filter(|c| !c.ot.contains(OT::Draft)).collect()
+}
+
+/// Writes `cards` to the target, xyyz targets ending with `trailing_comments` or those of the layout.
+fn write_cards(cards: &mut Vec<Card>, trailing_comments: &[String], args: &Args) {
+    let mut trailing_comments = trailing_comments.to_vec();
+    if let (Some(layout), Format::Xyyz) = (args.layout.as_ref(), guess_format(&args.to, Some(args.to_format))) {
+        let layout = Xyyz::parse_document(&std::fs::read_to_string(layout).unwrap_or_else(|e| panic!("Read file {} failed: {e}", layout)));
+        Xyyz::apply_layout(cards, &layout.cards);
+        trailing_comments = layout.trailing_comments;
+    }
+    if matches!(guess_format(&args.to, Some(args.to_format)), Format::SQL | Format::CDB) {
+        cards.iter().flat_map(|c| SQL::warnings(c, args.to_dialect)).chain(packs::conflicts(cards)).for_each(|w| eprintln!("{}", w));
     }
     match guess_format(&args.to, Some(args.to_format)) {
-        Format::Xyyz => write(args.to, cards.iter().map(|c| Xyyz::to_string(c)).collect::<Vec<_>>().join("\n\n")),
-        Format::SQL => write(args.to, cards.iter().map(|c| SQL::to_string(c)).collect::<Vec<_>>().join("\n\n")),
+        Format::Xyyz => write(&args.to, XyyzDocument { cards: cards.clone(), trailing_comments }.to_string_with(args.header_style)),
+        Format::SQL => write(&args.to, cards.iter().map(|c| SQL::to_string_with(c, args.to_dialect)).collect::<Vec<_>>().join("\n\n")),
         #[cfg(not(target_arch = "wasm32"))]
-        Format::CDB => Ok(CDB::save_to(&cards, &args.to)),
-        Format::Script => Ok(Script::save_to(&cards, &args.to)),
+        Format::CDB => Ok(CDB::save_to(cards, &args.to, args.to_dialect)),
+        Format::Script => Ok(Script::save_to(cards, &args.to)),
         _ => unimplemented!("Unimplemented type of target.")
     }.expect("Write file failed");
 }
+
+fn main() {
+    let args = Args::parse_from(preprocess());
+    read_string_conf(&args.strings);
+    MAX_LINE_LENGTH.set(args.max_line_length).expect("MAX_LINE_LENGTH already inited.");
+    // Draft cards still hold their codes, for the commands that hand out or move codes.
+    let XyyzDocument { cards: every_card, trailing_comments } = read_every_card(&args);
+    let mut cards = read_cards(every_card.clone(), &args);
+    let mut lists = args.lflist.iter()
+        .flat_map(|l| lflist::parse(&std::fs::read_to_string(l).unwrap_or_else(|e| panic!("Read file {} failed: {e}", l))))
+        .collect::<Vec<_>>();
+    for (list, code) in lflist::unknown_codes(&lists, &cards) {
+        eprintln!("Card {} of list {} is not loaded.", code, list);
+    }
+    // Limits written in the sources are what the lflist command writes back.
+    if !lists.is_empty() && !matches!(args.command, Some(Command::Lflist)) {
+        lflist::annotate(&mut cards, &lists);
+    }
+    match &args.command {
+        None => write_cards(&mut cards, &trailing_comments, &args),
+        Some(Command::Lint { allow, reference, glossary, fix }) => {
+            let glossary = glossary.as_ref()
+                .map(|g| lint::Glossary::parse(&std::fs::read_to_string(g).unwrap_or_else(|e| panic!("Read file {} failed: {e}", g))))
+                .unwrap_or_default();
+            if *fix {
+                for source in expand_sources(&args).iter().filter(|s| matches!(guess_format(s, args.from_format), Format::Xyyz)) {
+                    let text = std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source));
+                    let fixed = glossary.fix_source(&text);
+                    if fixed != text { write(source, fixed).expect("Write file failed"); }
+                }
+                cards = read_cards(read_every_card(&args).cards, &args);
+            }
+            let options = lint::LintOptions {
+                allow: allow.clone(),
+                official: reference.iter().flat_map(|r| read_source(r, &args)).collect(),
+                glossary
+            };
+            let diagnostics = lint::lint(&cards, &options);
+            for diagnostic in diagnostics.iter() {
+                println!("{}", serde_json::to_string(diagnostic).unwrap());
+            }
+            if diagnostics.iter().any(|d| d.severity == lint::Severity::Error) {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::References { reference, mentions }) => {
+            let official = reference.iter().flat_map(|r| read_source(r, &args)).collect::<Vec<_>>();
+            let index = references::ReferenceIndex::build(&cards, &official);
+            if let Some(code) = mentions {
+                match cards.iter().chain(official.iter()).find(|c| c.code == *code) {
+                    Some(card) => for mentioned_by in index.mentioning(card) { println!("{}", mentioned_by) },
+                    None => eprintln!("Card {} is not loaded.", code)
+                }
+            } else {
+                for reference in index.references.iter() {
+                    println!("{}", serde_json::to_string(reference).unwrap());
+                }
+            }
+        }
+        Some(Command::Allocate { range, reference }) => {
+            let official = reference.iter().flat_map(|r| read_source(r, &args)).collect::<Vec<_>>();
+            let used = every_card.iter().chain(official.iter()).flat_map(|c| [c.code, c.alias]);
+            let mut allocator = allocator::CodeAllocator::new(range.clone(), used);
+            for source in expand_sources(&args).iter().filter(|s| matches!(guess_format(s, args.from_format), Format::Xyyz)) {
+                let text = std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source));
+                let mut source_cards = Xyyz::from_string(&text);
+                let assigned = allocator.assign(&mut source_cards);
+                if assigned.len() > 0 {
+                    write(source, allocator::write_codes(&text, &source_cards, &assigned)).expect("Write file failed");
+                }
+                eprintln!("Assigned {} codes in {}.", assigned.len(), source);
+            }
+        }
+        Some(Command::Renumber { mapping, block, base, script, deck }) => {
+            let mut renumbering = mapping.as_ref()
+                .map(|m| renumber::Renumbering::parse(&std::fs::read_to_string(m).unwrap_or_else(|e| panic!("Read file {} failed: {e}", m))))
+                .unwrap_or_default();
+            if let (Some(block), Some(base)) = (block, base) {
+                match renumber::Renumbering::block(block.clone(), *base) {
+                    Ok(block) => renumbering.codes.extend(block.codes),
+                    Err(e) => { eprintln!("{}", e); std::process::exit(1); }
+                }
+            }
+            let collisions = renumbering.collisions(&every_card);
+            if !collisions.is_empty() {
+                eprintln!("Codes {:?} are already used by cards that are not renumbered.", collisions);
+                std::process::exit(1);
+            }
+            let mut failed = false;
+            for source in expand_sources(&args).iter() {
+                match guess_format(source, args.from_format) {
+                    Format::Xyyz => {
+                        let text = std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source));
+                        let renumbered = renumbering.rewrite_xyyz(&text);
+                        if renumbered != text { write(source, renumbered).expect("Write file failed"); }
+                    }
+                    Format::CDB => failed |= !CDB::renumber(source, &renumbering.codes),
+                    _ => eprintln!("Renumbering {} is not supported, skipped.", source)
+                }
+            }
+            if let Some(script) = script {
+                failed |= !renumbering.renumber_scripts(script);
+            }
+            for deck in deck.iter() {
+                let text = std::fs::read_to_string(deck).unwrap_or_else(|e| panic!("Read file {} failed: {e}", deck));
+                write(deck, renumbering.rewrite_ydk(&text)).expect("Write file failed");
+            }
+            eprintln!("Renumbered {} cards.", cards.iter().filter(|c| renumbering.codes.contains_key(&c.code)).count());
+            if failed { std::process::exit(1); }
+        }
+        Some(Command::Archetype { set, setcode, name }) => {
+            let setnames = SET_NAMES.load();
+            let Some(old_setcode) = archetype::parse_setcode(set).ok().or(setnames.iter().find(|(_, v)| *v == set).map(|(k, _)| *k)) else {
+                eprintln!("Can't recoginize set {}. ", set);
+                std::process::exit(1);
+            };
+            let new_setcode = setcode.unwrap_or(old_setcode);
+            let change = archetype::ArchetypeChange {
+                setcode: old_setcode,
+                new_setcode,
+                name: setnames.get(&old_setcode).cloned(),
+                new_name: name.clone().or(setnames.get(&old_setcode).cloned())
+            };
+            // Sub-archetypes move along, so their new numbers may be taken too.
+            let collisions = change.collisions(&every_card, setnames.keys().copied());
+            for collision in collisions.iter() {
+                match setnames.get(collision) {
+                    Some(name) => eprintln!("Setcode 0x{:X} is already used by {}.", collision, name),
+                    None => eprintln!("Setcode 0x{:X} is already used by cards.", collision)
+                }
+            }
+            if !collisions.is_empty() { std::process::exit(1); }
+            for source in expand_sources(&args).iter() {
+                match guess_format(source, args.from_format) {
+                    Format::Xyyz => {
+                        let text = std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source));
+                        let changed = change.rewrite_xyyz(&text);
+                        if changed != text { write(source, changed).expect("Write file failed"); }
+                    }
+                    Format::CDB => {
+                        let mut source_cards = CDB::open(source, args.from_dialect);
+                        let changed = change.apply(&mut source_cards);
+                        let source_cards = source_cards.into_iter().enumerate().filter(|(i, _)| changed.contains(i)).map(|(_, c)| c).collect::<Vec<_>>();
+                        if !CDB::update_setcodes(source, &source_cards) { std::process::exit(1); }
+                    }
+                    _ => eprintln!("Changing archetypes in {} is not supported, skipped.", source)
+                }
+            }
+            for strings in args.strings.iter() {
+                let text = std::fs::read_to_string(strings).unwrap_or_else(|e| panic!("Read file {} failed: {e}", strings));
+                let changed = change.rewrite_string_conf(&text);
+                if changed != text { write(strings, changed).expect("Write file failed"); }
+            }
+            eprintln!("Changed archetype of {} cards.", change.apply(&mut cards).len());
+        }
+        Some(Command::Fmt { check }) => {
+            let mut unformatted = 0;
+            for source in expand_sources(&args).iter().filter(|s| matches!(guess_format(s, args.from_format), Format::Xyyz)) {
+                let text = std::fs::read_to_string(source).unwrap_or_else(|e| panic!("Read file {} failed: {e}", source));
+                let formatted = Xyyz::format_source(&text);
+                if formatted == text { continue; }
+                unformatted += 1;
+                if *check { eprintln!("{} is not formatted.", source) } else { write(source, formatted).expect("Write file failed") }
+            }
+            if *check && unformatted > 0 { std::process::exit(1); }
+        }
+        Some(Command::Packs { dir }) => {
+            std::fs::create_dir_all(dir).unwrap_or_else(|e| panic!("Create folder {} failed: {e}", dir));
+            let lists = packs::pack_lists(&cards);
+            for list in lists.iter() {
+                write(std::path::Path::new(dir).join(list.file_name()), list.to_ydk()).expect("Write file failed");
+            }
+            eprintln!("Wrote {} packs.", lists.len());
+        }
+        Some(Command::Pool { before, region, lflist, ydk, name }) => {
+            let mut cards = pool::released_before(cards, *before, *region);
+            let name = name.clone().unwrap_or_else(|| {
+                let date = format!("{}-{:02}-{:02}", before.0, before.1, before.2);
+                region.map_or(date.clone(), |r| format!("{} {:?}", date, r))
+            });
+            if let Some(lflist) = lflist {
+                write(lflist, pool::to_lflist(&cards, &name)).expect("Write file failed");
+            }
+            if let Some(ydk) = ydk {
+                write(ydk, ydk::write(&name, &ydk::Deck::from_codes(cards.iter().map(|c| c.code)))).expect("Write file failed");
+            }
+            eprintln!("{} cards are released by {}.", cards.len(), name);
+            write_cards(&mut cards, &[], &args);
+        }
+        Some(Command::Lflist) => {
+            lflist::apply_limits(&mut lists, &cards);
+            write(&args.to, lflist::write(&lists)).expect("Write file failed");
+        }
+        Some(Command::Deck { ydk, extract, annotate }) => {
+            let mut problems = 0;
+            let mut deck_cards = Vec::<Card>::new();
+            for path in ydk.iter() {
+                let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Read file {} failed: {e}", path));
+                let deck = ydk::Deck::parse(&text);
+                for problem in deck.check(&cards) {
+                    eprintln!("{}:{}: {} [{}]", path, problem.line, problem.message, problem.rule);
+                    problems += 1;
+                }
+                for card in deck.cards(&cards) {
+                    if !deck_cards.iter().any(|c| c.code == card.code) { deck_cards.push(card) }
+                }
+                if *annotate {
+                    let annotated = ydk::annotate(&text, &cards);
+                    if annotated != text { write(path, annotated).expect("Write file failed") }
+                }
+            }
+            if *extract {
+                eprintln!("Extracted {} cards.", deck_cards.len());
+                write_cards(&mut deck_cards, &[], &args);
+            } else if problems > 0 {
+                std::process::exit(1);
+            }
+        }
+    }
+}
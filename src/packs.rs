@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::card::Card;
+use crate::transformers::SQL;
+use crate::ydk::{self, Deck};
+
+/// The cards of one pack, for the pack browser of ygopro clients.
+pub struct PackList {
+    /// `SD1-JP` of `SD1-JP001`.
+    pub prefix: String,
+    pub pack: String,
+    pub date: String,
+    /// Set numbers and codes, ordered by set number.
+    pub cards: Vec<(String, u32)>
+}
+
+/// `INFO-JP012` sorts by 12, so that unpadded set numbers keep their order.
+fn set_number_key(pack_id: &str) -> (u32, String) {
+    let digits = pack_id.chars().rev().take_while(|c| c.is_ascii_digit()).collect::<Vec<_>>();
+    let number = digits.iter().rev().collect::<String>().parse().unwrap_or_default();
+    (number, pack_id.to_string())
+}
+
+/// Groups cards by pack, oldest pack first. The prints of a set in each region are packs of their own.
+///
+/// A card is listed in the pack of each of its printings, and of each card read with the same code.
+pub fn pack_lists(cards: &[Card]) -> Vec<PackList> {
+    let mut packs = HashMap::<String, PackList>::new();
+    let printings = cards.iter().flat_map(|c| c.printings().map(|p| (c.code, p))).filter(|(code, p)| *code > 0 && !p.pack_id.is_empty());
+    for (code, pack) in printings {
+        let prefix = SQL::pack_prefix(&pack.pack_id).to_string();
+        let list = packs.entry(prefix.clone()).or_insert_with(|| PackList { prefix, pack: String::new(), date: String::new(), cards: Vec::new() });
+        if list.pack.is_empty() { list.pack = pack.pack.clone() }
+        if list.date.is_empty() { list.date = pack.date.clone() }
+        if !list.cards.contains(&(pack.pack_id.clone(), code)) { list.cards.push((pack.pack_id.clone(), code)) }
+    }
+    let mut lists = packs.into_values().collect::<Vec<_>>();
+    for list in lists.iter_mut() {
+        list.cards.sort_by_key(|(pack_id, _)| set_number_key(pack_id));
+    }
+    // Packs without a date go last.
+    lists.sort_by(|a, b| (a.date.is_empty(), &a.date, &a.prefix).cmp(&(b.date.is_empty(), &b.date, &b.prefix)));
+    lists
+}
+
+/// Packs that cards name or date differently, where the first card to name or date a pack decides.
+pub fn conflicts(cards: &[Card]) -> Vec<String> {
+    let mut packs = HashMap::<&str, (&str, &str)>::new();
+    let mut conflicts = Vec::new();
+    for pack in cards.iter().flat_map(|c| c.printings()) {
+        let prefix = SQL::pack_prefix(&pack.pack_id);
+        let (name, date) = packs.entry(prefix).or_default();
+        for (field, kept, value) in [("named", name, &pack.pack), ("dated", date, &pack.date)] {
+            if value.is_empty() { continue }
+            if kept.is_empty() { *kept = value }
+            else if kept != value { conflicts.push(format!("Pack {} is {} both {} and {}, {} is kept.", prefix, field, kept, value, kept)) }
+        }
+    }
+    conflicts
+}
+
+impl PackList {
+    /// `2024-04-27 INFO-JP.ydk`, so that a folder of packs lists them by release date.
+    pub fn file_name(&self) -> String {
+        let name = format!("{}{}", if self.date.is_empty() { String::new() } else { format!("{} ", self.date) }, self.prefix);
+        format!("{}.ydk", name.replace(['/', '\\', ':'], "_"))
+    }
+
+    pub fn to_ydk(&self) -> String {
+        let title = [&self.prefix, &self.pack, &self.date].iter().filter(|s| !s.is_empty()).map(|s| s.as_str()).collect::<Vec<_>>().join(" ");
+        ydk::write(&title, &Deck::from_codes(self.cards.iter().map(|(_, code)| *code)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::packs::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_pack_lists() {
+        let text = "[INFO-JP012 SR 2024-04-27] 甲(100) 通常魔法\n\n[INFO-JP2 N] 乙(101) 通常魔法\n\n[SD1-JP001 N 2002-03-21] 丙(102) 通常魔法\n卡包：[SD1-EN001 N 2002-03-08]\n\n[PROMO-JP001] 丁(103) 通常魔法\n\n[SD1-JP003] 乙(101) 通常魔法";
+        let lists = pack_lists(&Xyyz::from_string(text));
+        assert_eq!(lists.iter().map(|l| l.file_name()).collect::<Vec<_>>(), vec!["2002-03-08 SD1-EN.ydk", "2002-03-21 SD1-JP.ydk", "2024-04-27 INFO-JP.ydk", "PROMO-JP.ydk"]);
+        assert_eq!(lists[0].cards, vec![("SD1-EN001".to_string(), 102)]);
+        assert_eq!(lists[1].cards, vec![("SD1-JP001".to_string(), 102), ("SD1-JP003".to_string(), 101)]);
+        assert_eq!(lists[2].to_ydk(), "#created by cdb-transformer\n#INFO-JP 2024-04-27\n#main\n101\n100\n#extra\n!side\n");
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::card::Card;
+use crate::constants::Region;
+use crate::lflist::{self, Banlist, Entry};
+
+/// Parses `2024-04-27` or `2024-4-27` into a comparable date.
+pub fn parse_date(value: &str) -> Result<(u32, u32, u32), String> {
+    let parts = value.trim().split("-").map(|p| p.parse::<u32>()).collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Bad date {}: {}", value, e))?;
+    match parts[..] {
+        [year, month, day] if (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day) => Ok((year, month, day)),
+        _ => Err(format!("Date {} should look like 2024-04-27.", value))
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31
+    }
+}
+
+/// The first release of every code, over all the printings read. Printings of the other region don't count.
+pub fn first_releases(cards: &[Card], region: Option<Region>) -> HashMap<u32, (u32, u32, u32)> {
+    let mut releases = HashMap::new();
+    for (card, pack) in cards.iter().flat_map(|c| c.printings().map(move |p| (c, p))) {
+        let Ok(date) = parse_date(&pack.date) else { continue };
+        if region.is_some() && Region::of_pack_id(&pack.pack_id).is_some_and(|r| Some(r) != region) { continue }
+        let first = releases.entry(card.code).or_insert(date);
+        if date < *first { *first = date }
+    }
+    releases
+}
+
+/// Keeps one of each card first released on or before `date`, and legal in `region` by its OT.
+///
+/// Cards without a release date are left out.
+pub fn released_before(cards: Vec<Card>, date: (u32, u32, u32), region: Option<Region>) -> Vec<Card> {
+    let releases = first_releases(&cards, region);
+    let mut seen = HashSet::new();
+    cards.into_iter()
+        .filter(|c| releases.get(&c.code).is_some_and(|d| *d <= date))
+        .filter(|c| region.is_none_or(|r| c.ot.contains(r.ot())))
+        .filter(|c| seen.insert(c.code))
+        .collect()
+}
+
+/// An lflist.conf whitelist that allows 3 of every card.
+pub fn to_lflist(cards: &[Card], name: &str) -> String {
+    let mut list = Banlist::new(name);
+    list.whitelist = true;
+    list.entries = cards.iter().map(|c| Entry { code: c.code, count: 3, comment: c.name.clone(), comments: Vec::new() }).collect();
+    lflist::write(&[list])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::constants::Region;
+    use crate::pool::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_released_before() {
+        let text = "[LOB-EN001 UR 2002-03-08] 青眼白龙(89631139) 光 8星 龙/通常 3000 2500\n\n[SD1-JP001 N 1999-02-04] 青眼白龙(89631139) 光 8星 龙/通常 3000 2500\n\n\
+            [INFO-JP000 QCSER 2024-04-27] 骄傲与灵魂之龙(100) 暗 8星 龙/特殊召唤 2500 2500 (OCG)\n\n原质(101) 通常魔法";
+        let cards = Xyyz::from_string(text);
+        assert_eq!(parse_date("2000-1-1"), Ok((2000, 1, 1)));
+        assert!(parse_date("2000-13-1").is_err());
+        assert!(parse_date("2024-02-31").is_err());
+        assert!(parse_date("2024-04-00").is_err());
+        assert!(parse_date("2024-04-31").is_err());
+        assert_eq!(parse_date("2024-02-29"), Ok((2024, 2, 29)));
+        assert!(parse_date("2023-02-29").is_err());
+        assert!(parse_date("1900-02-29").is_err());
+        assert!(parse_date("2000-02-29").is_ok());
+        let pool = released_before(Xyyz::from_string(text), parse_date("2000-01-01").unwrap(), None);
+        assert_eq!(pool.iter().map(|c| c.code).collect::<Vec<_>>(), vec![89631139]);
+        assert!(released_before(Xyyz::from_string(text), (2000, 1, 1), Some(Region::Tcg)).is_empty());
+        let pool = released_before(cards, (2024, 12, 31), Some(Region::Tcg));
+        assert_eq!(pool.iter().map(|c| c.code).collect::<Vec<_>>(), vec![89631139]);
+        assert_eq!(to_lflist(&pool, "2024.12 TCG"), "#[2024.12 TCG]\n!2024.12 TCG\n$whitelist\n89631139 3 --青眼白龙\n");
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::card::{Card, Provenance, Range};
+use crate::transformers::SET_NAMES;
+
+/// A 「name」 quoted inside the effect text of a card.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reference {
+    pub code: u32,
+    /// Index of the quoting card in the cards the index was built from, as codes repeat or are 0.
+    #[serde(skip)]
+    pub card: usize,
+    pub name: String,
+    pub cards: Vec<u32>,
+    pub setcode: Option<u16>,
+    pub range: Option<Range>,
+    pub provenance: Provenance
+}
+
+impl Reference {
+    pub fn is_resolved(&self) -> bool {
+        !self.cards.is_empty() || self.setcode.is_some()
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        self.cards.len() > 1
+    }
+}
+
+/// Outermost 「」 contents of a text, in order.
+pub fn extract_references(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    for c in text.chars() {
+        match c {
+            '「' => { if depth > 0 { current.push(c) } depth += 1; }
+            '」' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 { names.push(std::mem::take(&mut current)) } else { current.push(c) }
+            }
+            _ => if depth > 0 { current.push(c) }
+        }
+    }
+    names
+}
+
+pub struct ReferenceIndex {
+    pub references: Vec<Reference>
+}
+
+impl ReferenceIndex {
+    /// Resolves references of `cards` against themselves and the optional `official` cards.
+    pub fn build(cards: &[Card], official: &[Card]) -> ReferenceIndex {
+        let setnames = SET_NAMES.load();
+        let mut names = HashMap::<&str, Vec<u32>>::new();
+        for card in cards.iter().chain(official.iter()) {
+            // Alternative arts share the name of the card they alias.
+            let code = if card.alias > 0 { card.alias } else { card.code };
+            let codes = names.entry(card.name.as_str()).or_default();
+            if !codes.contains(&code) { codes.push(code) }
+        }
+        let mut references = Vec::new();
+        for (i, card) in cards.iter().enumerate() {
+            for name in extract_references(&card.desc) {
+                // Counters are quoted the same way but are not cards.
+                if name.ends_with("指示物") { continue; }
+                references.push(Reference {
+                    code: card.code,
+                    card: i,
+                    cards: names.get(name.as_str()).cloned().unwrap_or_default(),
+                    setcode: setnames.iter().find(|(_, v)| **v == name).map(|(k, _)| *k),
+                    name,
+                    range: card.range,
+                    provenance: card.provenance.clone()
+                });
+            }
+        }
+        ReferenceIndex { references }
+    }
+
+    /// Codes of cards whose text mentions `card`, directly, by the card it aliases or by one of its archetypes.
+    pub fn mentioning(&self, card: &Card) -> Vec<u32> {
+        let setcodes = card.setcodes();
+        // References resolve alternative arts to the card they alias.
+        let code = if card.alias > 0 { card.alias } else { card.code };
+        let mut codes = Vec::new();
+        for reference in self.references.iter() {
+            if (reference.cards.contains(&code) || reference.setcode.is_some_and(|s| setcodes.contains(&s)))
+                && !codes.contains(&reference.code) {
+                codes.push(reference.code);
+            }
+        }
+        codes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::references::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_references() {
+        assert_eq!(extract_references("「「A」的B」和「C」"), vec!["「A」的B", "C"]);
+        let cards = Xyyz::from_string("
+原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000
+起点之果。
+
+原质贝塔(10001) 暗 3星 恶魔 1000 1000
+①：从卡组把1只「原质阿尔法」加入手卡，再放置1个「原质指示物」。②：「原质伽马」不能发动。
+
+原质阿尔法(10002=>10000) 暗 3星 恶魔/通常 1000 1000
+起点之果。
+");
+        let index = ReferenceIndex::build(&cards, &[]);
+        assert_eq!(index.references.len(), 2);
+        assert_eq!(index.references[1].card, 1);
+        assert!(!index.references[1].is_resolved());
+        assert_eq!(index.mentioning(&cards[0]), vec![10001]);
+        assert_eq!(index.mentioning(&cards[2]), vec![10001]);
+    }
+}
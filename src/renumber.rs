@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+use crate::card::Card;
+use crate::cst::{Document, TokenKind};
+
+static SCRIPT_CODE_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"\b(c?)(\d+)\b").unwrap() });
+
+/// Old codes and the new codes they move to.
+#[derive(Debug, Default)]
+pub struct Renumbering {
+    pub codes: HashMap<u32, u32>
+}
+
+impl Renumbering {
+    /// Reads one `old new` pair per line, `#` starts a comment line.
+    pub fn parse(text: &str) -> Renumbering {
+        let mut codes = HashMap::new();
+        for line in text.split("\n").map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with("#")) {
+            let parts = line.split_whitespace().map(|p| p.parse::<u32>()).collect::<Vec<_>>();
+            match parts.as_slice() {
+                [Ok(old), Ok(new)] => { codes.insert(*old, *new); }
+                _ => eprintln!("Can't recognize code pair {}", line)
+            }
+        }
+        Renumbering { codes }
+    }
+
+    /// Moves every code of `block` to the same offset from `base`, unless that goes past the largest code.
+    pub fn block(block: RangeInclusive<u32>, base: u32) -> Result<Renumbering, String> {
+        let codes = block.clone()
+            .map(|code| (code - block.start()).checked_add(base).map(|new| (code, new)))
+            .collect::<Option<HashMap<_, _>>>()
+            .ok_or(format!("Moving {}-{} to {} goes past code {}.", block.start(), block.end(), base, u32::MAX))?;
+        Ok(Renumbering { codes })
+    }
+
+    pub fn get(&self, code: u32) -> u32 {
+        self.codes.get(&code).copied().unwrap_or(code)
+    }
+
+    /// New codes that cards outside of the renumbering already use, or that several old codes move to.
+    pub fn collisions(&self, cards: &[Card]) -> Vec<u32> {
+        let kept = cards.iter().map(|c| c.code).filter(|code| !self.codes.contains_key(code)).collect::<HashSet<_>>();
+        let mut seen = HashSet::new();
+        let mut collisions = self.codes.values().copied().filter(|new| kept.contains(new) || !seen.insert(*new)).collect::<Vec<_>>();
+        collisions.sort();
+        collisions.dedup();
+        collisions
+    }
+
+    /// Rewrites `(code=>alias)` on the header lines of an xyyz source, leaving the rest as it is.
+    pub fn rewrite_xyyz(&self, source: &str) -> String {
+        let mut document = Document::parse(source);
+        for card in document.cards_mut() {
+            for kind in [TokenKind::Code, TokenKind::Alias] {
+                if let Some(code) = card.get(kind).and_then(|c| c.parse().ok()) { card.set(kind, &self.get(code).to_string()); }
+            }
+        }
+        document.to_string()
+    }
+
+    /// Rewrites `c<code>` and bare `<code>` references inside a card script.
+    ///
+    /// Bare numbers are only codes with 8 digits or more, so that damage, ATK and counts stay as they are.
+    pub fn rewrite_script(&self, script: &str) -> String {
+        SCRIPT_CODE_REGEX.replace_all(script, |groups: &Captures| {
+            if groups[1].is_empty() && groups[2].len() < 8 { return groups[0].to_string() }
+            match groups[2].parse::<u32>().ok().and_then(|code| self.codes.get(&code)) {
+                Some(code) => format!("{}{}", &groups[1], code),
+                None => groups[0].to_string()
+            }
+        }).to_string()
+    }
+
+    /// Rewrites the code lines of a ydk deck, keeping `#main`, `!side` and comments.
+    pub fn rewrite_ydk(&self, deck: &str) -> String {
+        deck.split("\n").map(|line| match line.trim().parse::<u32>() {
+            Ok(code) => line.replace(line.trim(), &self.get(code).to_string()),
+            Err(_) => line.to_string()
+        }).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Renames the `{id}` scripts of renumbered cards and rewrites references in them. Returns whether it did.
+    ///
+    /// New scripts are written next to the old ones first, so that a failed write leaves every old script in place.
+    pub fn renumber_scripts(&self, path: &str) -> bool {
+        // Read everything before writing, so that swapped codes don't overwrite each other.
+        let scripts = self.codes.iter()
+            .filter_map(|(old, new)| {
+                let old_path = path.replace("{id}", &old.to_string());
+                std::fs::read_to_string(&old_path).ok().map(|script| (old_path, path.replace("{id}", &new.to_string()), script))
+            })
+            .collect::<Vec<_>>();
+        let temporary = |new_path: &str| format!("{}.renumbered", new_path);
+        for (_, new_path, script) in scripts.iter() {
+            if let Err(e) = std::fs::write(temporary(new_path), self.rewrite_script(script)) {
+                eprintln!("Write file {} failed: {}, scripts are left as they are.", temporary(new_path), e);
+                scripts.iter().for_each(|(_, new_path, _)| { std::fs::remove_file(temporary(new_path)).ok(); });
+                return false;
+            }
+        }
+        let new_paths = scripts.iter().map(|(_, new_path, _)| new_path.as_str()).collect::<HashSet<_>>();
+        for (old_path, new_path, _) in scripts.iter() {
+            if let Err(e) = std::fs::rename(temporary(new_path), new_path) {
+                eprintln!("Rename file {} failed: {}", temporary(new_path), e);
+                return false;
+            }
+            if !new_paths.contains(old_path.as_str()) { std::fs::remove_file(old_path).ok(); }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::renumber::*;
+    use crate::transformers::Xyyz;
+
+    #[test]
+    fn test_renumber() {
+        let renumbering = Renumbering::block(100..=199, 200).unwrap();
+        let source = "日食爆龙(100) 暗 LINK-2 龙/连接 1600 [↓][↘] (Custom)\n把「日食爆龙(100)」放置。\n\n青眼白龙(101 => 100) 通常魔法\n传说之龙。\n\n机械(123)号(102) 通常魔法";
+        let cards = Xyyz::from_string(source);
+        assert_eq!(renumbering.rewrite_xyyz(source), "日食爆龙(200) 暗 LINK-2 龙/连接 1600 [↓][↘] (Custom)\n把「日食爆龙(100)」放置。\n\n青眼白龙(201 => 200) 通常魔法\n传说之龙。\n\n机械(123)号(202) 通常魔法");
+        assert_eq!(renumbering.collisions(&cards), Vec::<u32>::new());
+        assert_eq!(Renumbering::block(100..=100, 101).unwrap().collisions(&cards), vec![101]);
+        assert!(Renumbering::block(100..=200, u32::MAX - 50).is_err());
+        let scripts = Renumbering::block(100000100..=100000199, 100000200).unwrap();
+        assert_eq!(scripts.rewrite_script("local s,id=GetID()\nfunction c100000100.initial_effect(c)\n\tc:SetUniqueOnField(1,0,100000101)\n\tDuel.Damage(1-tp,150,REASON_EFFECT)\nend"),
+            "local s,id=GetID()\nfunction c100000200.initial_effect(c)\n\tc:SetUniqueOnField(1,0,100000201)\n\tDuel.Damage(1-tp,150,REASON_EFFECT)\nend");
+        assert_eq!(renumbering.rewrite_ydk("#main\n100\n89631139\n!side\n101\n"), "#main\n200\n89631139\n!side\n201\n");
+
+        let dir = std::env::temp_dir().join(format!("renumber_scripts_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("c100.lua"), "--first").unwrap();
+        std::fs::write(dir.join("c101.lua"), "--second").unwrap();
+        let swap = Renumbering { codes: HashMap::from([(100, 101), (101, 100)]) };
+        assert!(swap.renumber_scripts(dir.join("c{id}.lua").to_str().unwrap()));
+        assert_eq!(std::fs::read_to_string(dir.join("c100.lua")).unwrap(), "--second");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::card::Range;
+use crate::cst::{Document, Item, LineKind, TokenKind};
+
+/// The same piece of text, counted in UTF-8 bytes, Unicode scalar values and UTF-16 code units.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextSpan {
+    pub bytes: Range,
+    pub chars: Range,
+    pub utf16: Range
+}
+
+/// Byte and UTF-16 offsets of every char offset of a text.
+pub struct OffsetTable {
+    bytes: Vec<usize>,
+    utf16: Vec<usize>
+}
+
+impl OffsetTable {
+    pub fn new(text: &str) -> OffsetTable {
+        let mut bytes = vec![0];
+        let mut utf16 = vec![0];
+        for c in text.chars() {
+            bytes.push(bytes.last().unwrap() + c.len_utf8());
+            utf16.push(utf16.last().unwrap() + c.len_utf16());
+        }
+        OffsetTable { bytes, utf16 }
+    }
+
+    pub fn from_chars(&self, start: usize, end: usize) -> TextSpan {
+        TextSpan {
+            bytes: (self.bytes[start]..self.bytes[end]).into(),
+            chars: (start..end).into(),
+            utf16: (self.utf16[start]..self.utf16[end]).into()
+        }
+    }
+
+    pub fn from_bytes(&self, start: usize, end: usize) -> TextSpan {
+        self.from_chars(self.bytes.partition_point(|b| *b < start), self.bytes.partition_point(|b| *b < end))
+    }
+
+    /// UTF-16 offsets inside a surrogate pair move to the char it belongs to.
+    pub fn from_utf16(&self, start: usize, end: usize) -> TextSpan {
+        self.from_chars(self.utf16.partition_point(|u| *u < start), self.utf16.partition_point(|u| *u < end))
+    }
+}
+
+/// Where a card and each of its fields are in an xyyz source.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardSpans {
+    pub code: u32,
+    pub card: TextSpan,
+    pub name: Option<TextSpan>,
+    /// The code inside the parentheses of the header.
+    pub id: Option<TextSpan>,
+    pub alias: Option<TextSpan>,
+    pub attribute: Option<TextSpan>,
+    pub level: Option<TextSpan>,
+    /// Race and subtypes of a monster, or the type of a spell or trap.
+    pub race: Option<TextSpan>,
+    /// Attack, defense or link markers, and maximum attack.
+    pub stats: Option<TextSpan>,
+    pub desc_lines: Vec<TextSpan>
+}
+
+/// Spans of every card in an xyyz source.
+pub fn card_spans(text: &str) -> Vec<CardSpans> {
+    let table = OffsetTable::new(text);
+    let mut spans = Vec::new();
+    // Byte offset of the item being read.
+    let mut offset = 0;
+    for item in Document::parse(text).items {
+        let card = match item {
+            Item::Card(card) => card,
+            Item::Line(line) => { offset += line.to_string().len(); continue }
+        };
+        // Byte ranges of the fields of the header, by kind.
+        let mut fields = Vec::new();
+        let mut desc_lines = Vec::new();
+        let start = offset;
+        let mut end = offset;
+        for (i, line) in card.lines.iter().enumerate() {
+            let mut index = offset;
+            for token in line.tokens.iter() {
+                if i == 0 { fields.push((token.kind, index..index + token.text.len())) }
+                index += token.text.len();
+            }
+            if matches!(line.kind, LineKind::Description | LineKind::Pendulum) { desc_lines.push(table.from_bytes(offset, index)) }
+            end = index;
+            offset = index + line.ending.len();
+        }
+        // The span from the first field of `kinds` to the last one.
+        let span = |kinds: &[TokenKind]| {
+            let mut ranges = fields.iter().filter(|(kind, _)| kinds.contains(kind)).map(|(_, range)| range);
+            let first = ranges.next()?;
+            Some(table.from_bytes(first.start, ranges.next_back().unwrap_or(first).end))
+        };
+        spans.push(CardSpans {
+            code: card.code(),
+            card: table.from_bytes(start, end),
+            name: span(&[TokenKind::Name]),
+            id: span(&[TokenKind::Code]),
+            alias: span(&[TokenKind::Alias]),
+            attribute: span(&[TokenKind::Attribute]),
+            level: span(&[TokenKind::Level]),
+            race: span(&[TokenKind::Race, TokenKind::Subtype, TokenKind::Type]),
+            stats: span(&[TokenKind::Attack, TokenKind::Defense, TokenKind::LinkMarkers, TokenKind::MaximumAttack]),
+            desc_lines
+        });
+    }
+    spans
+}
+
+#[cfg(test)]
+mod test {
+    use crate::spans::*;
+
+    #[test]
+    fn test_card_spans() {
+        let text = "原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\r\n起点之果😀。\r\n效果分类：0x1\r\n\r\n青眼白龙 通常魔法\n传说之龙。";
+        let spans = card_spans(text);
+        assert_eq!(spans.len(), 2);
+        let first = &spans[0];
+        assert_eq!(first.name.map(|s| s.chars), Some((0..5).into()));
+        assert_eq!(first.id.map(|s| (s.chars, s.bytes)), Some(((6..11).into(), (16..21).into())));
+        assert_eq!(first.attribute.map(|s| s.chars), Some((13..14).into()));
+        assert_eq!(first.level.map(|s| s.chars), Some((15..17).into()));
+        assert_eq!(first.race.map(|s| s.chars), Some((18..23).into()));
+        assert_eq!(first.stats.map(|s| s.chars), Some((24..33).into()));
+        assert_eq!(first.desc_lines.len(), 1);
+        assert_eq!(first.desc_lines[0].chars, (35..41).into());
+        assert_eq!(first.desc_lines[0].utf16, (35..42).into());
+        assert_eq!(first.card.chars.end, 51);
+        let second = &spans[1];
+        assert_eq!(second.name.map(|s| s.chars), Some((55..59).into()));
+        assert_eq!(second.race.map(|s| s.chars), Some((60..64).into()));
+        assert_eq!(second.card.chars.end, text.chars().count());
+    }
+}
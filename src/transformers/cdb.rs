@@ -4,23 +4,65 @@ use std::str::FromStr;
 
 use sqlite::Connection;
 
-use crate::card::{Card, CardTransformer};
+use crate::card::{Card, CardTransformer, PackInfo, Provenance};
 use crate::constants::*;
 
-use crate::transformers::{CREATE_TABLE_SQL, SQL, STR_FIELD_NAMES};
+use crate::transformers::{scope_to_ot, SQL, STR_FIELD_NAMES};
 
 pub struct CDB;
 
 impl CDB {
-    pub fn save_to(cards: &Vec<Card>, path: &str) {
+    pub fn save_to(cards: &Vec<Card>, path: &str, dialect: Dialect) {
         let connection = Connection::open(path).expect("Failed to open file.");
-        connection.execute(CREATE_TABLE_SQL).expect("create table failed");
-        let str = cards.iter().map(|c| SQL::to_string(c)).collect::<Vec<_>>().join("\n");
+        connection.execute(SQL::create_table_sql()).expect("create table failed");
+        let str = cards.iter().map(|c| SQL::to_string_with(c, dialect)).collect::<Vec<_>>().join("\n");
         connection.execute(str).expect("execute sql failed");
     }
 
-    pub fn from_connection(connection: Connection) -> Vec<Card> {
-        const QUERY: &str = "select * from datas join texts where datas.id = texts.id";
+    /// Moves rows of `codes` to their new ids, and aliases pointing at them along. Returns whether it did.
+    ///
+    /// Nothing changes if a new code is used by a card that stays, or if any statement fails.
+    pub fn renumber(path: &str, codes: &HashMap<u32, u32>) -> bool {
+        let connection = Connection::open(path).expect("Failed to open file.");
+        let mut kept = Vec::new();
+        connection.iterate("select id from datas", |row| {
+            if let Some(id) = row[0].1.and_then(|id| id.parse::<u32>().ok()).filter(|id| !codes.contains_key(id)) { kept.push(id) }
+            true
+        }).ok();
+        let collisions = codes.values().filter(|new| kept.contains(new)).collect::<Vec<_>>();
+        if !collisions.is_empty() {
+            eprintln!("Codes {:?} are used by cards of {} that are not renumbered, it is left alone.", collisions, path);
+            return false;
+        }
+        // Cdbs written by other tools lack some of the tables.
+        let tables = ["datas", "texts", "card_packs"].into_iter().filter(|t| table_exists(&connection, t)).collect::<Vec<_>>();
+        // Negative ids first, so that swapped codes never collide on the primary key.
+        let mut str = "begin;\n".to_string();
+        for (old, new) in codes.iter() {
+            for table in tables.iter() { str += &format!("update {} set id = {} where id = {};\n", table, -(*new as i64), old); }
+            str += &format!("update datas set alias = {} where alias = {};\n", -(*new as i64), old);
+        }
+        for table in tables.iter() { str += &format!("update {} set id = -id where id < 0;\n", table); }
+        str += "update datas set alias = -alias where alias < 0;\ncommit;\n";
+        execute_or_rollback(&connection, &str, path)
+    }
+
+    /// Writes the archetypes of `cards` over the rows with the same ids. Returns whether it did.
+    pub fn update_setcodes(path: &str, cards: &[Card]) -> bool {
+        let connection = Connection::open(path).expect("Failed to open file.");
+        let mut str = "begin;\n".to_string();
+        for card in cards {
+            str += &format!("update datas set setcode = {} where id = {};\n", card.setcode as i64, card.code);
+        }
+        execute_or_rollback(&connection, &(str + "commit;\n"), path)
+    }
+
+    pub fn open(path: &str, dialect: Dialect) -> Vec<Card> {
+        CDB::from_connection(sqlite::open(path).expect("Cannot open sqlite file"), dialect)
+    }
+
+    pub fn from_connection(connection: Connection, dialect: Dialect) -> Vec<Card> {
+        const QUERY: &str = "select datas.rowid as _rowid, * from datas join texts where datas.id = texts.id";
         let mut cards = Vec::new();
         connection.iterate(QUERY, |iter| {
             let mut h = HashMap::new();
@@ -34,26 +76,36 @@ impl CDB {
                 name: get(&h, "name"),
                 desc: get(&h, "desc"),
                 alias: get(&h, "alias"),
-                setcode: get(&h, "setcode"),
-                _type: Type::from_bits_retain(get(&h, "type")),
+                setcode: get_bits(&h, "setcode"),
+                extra_setcodes: Vec::new(),
+                _type: Type::from_bits_retain(get_bits(&h, "type")),
                 level: 0,
                 attribute: Attribute::empty(),
                 race: Race::empty(),
                 attack: 0,
                 defense: 0,
+                maximum_attack: 0,
                 lscale: 0,
                 rscale: 0,
                 link_marker: Linkmarkers::empty(),
                 ot: OT::from_bits_retain(get(&h, "ot")),
-                category: Category::from_bits_truncate(get(&h, "category")),
+                category: Category::from_bits_retain(get_bits(&h, "category")),
                 texts: Vec::new(),
                 pack: None,
-                range: None
+                reprints: Vec::new(),
+                range: None,
+                desc_ranges: Vec::new(),
+                provenance: Provenance { row: Some(get(&h, "_rowid")), ..Provenance::new(Format::CDB) },
+                comments: Vec::new(),
+                inner_comments: Vec::new(),
+                limits: Vec::new()
             };
+            let mut level = 0;
             if card._type.contains(Type::Monster) {
-                card.level = get(&h, "level");
+                level = get_bits(&h, "level");
+                card.level = level as u32;
                 card.attribute = Attribute::from_bits_retain(get(&h, "attribute"));
-                card.race = Race::from_bits_retain(get(&h, "race"));
+                card.race = Race::from_bits_retain(get_bits(&h, "race"));
                 card.attack = get(&h, "atk");
                 card.defense = get(&h, "def");
             }
@@ -73,21 +125,68 @@ impl CDB {
                     }
                 }
             }
+            SQL::read_maximum(&mut card, level);
+            if let Dialect::EDOPro = dialect {
+                let (ot, legend) = scope_to_ot(&Scope::from_bits_retain(get_bits(&h, "ot")));
+                card.ot = ot;
+                card._type |= legend;
+            }
             card.level = card.level & 0xff;
             cards.push(card);
             true
         }).ok();
+        // Cdbs written by other tools have no pack tables.
+        let text = |h: &HashMap<&str, &str>, key: &str| h.get(key).map(|s| s.to_string()).unwrap_or_default();
+        let mut packs = HashMap::<String, (String, String)>::new();
+        connection.iterate("select * from packs", |iter| {
+            let h = iter.iter().filter_map(|(name, value)| value.map(|v| (*name, v))).collect::<HashMap<_, _>>();
+            packs.insert(text(&h, "prefix"), (text(&h, "pack"), text(&h, "date")));
+            true
+        }).ok();
+        let mut card_packs = HashMap::<u32, Vec<PackInfo>>::new();
+        connection.iterate("select * from card_packs order by rowid", |iter| {
+            let h = iter.iter().filter_map(|(name, value)| value.map(|v| (*name, v))).collect::<HashMap<_, _>>();
+            let id = text(&h, "id").parse().unwrap_or_default();
+            let pack_id = text(&h, "pack_id");
+            let (pack, date) = packs.get(SQL::pack_prefix(&pack_id)).cloned().unwrap_or_default();
+            let rarity = text(&h, "rarity").split("/").filter(|r| !r.is_empty()).map(|r| r.to_string()).collect();
+            card_packs.entry(id).or_default().push(PackInfo { id, pack_id, pack, rarity, date, japanese_name: text(&h, "japanese_name") });
+            true
+        }).ok();
+        for card in cards.iter_mut() {
+            let mut printings = card_packs.remove(&card.code).unwrap_or_default().into_iter();
+            card.pack = printings.next();
+            card.reprints = printings.collect();
+        }
         cards
     }
 }
 
+fn table_exists(connection: &Connection, table: &str) -> bool {
+    let mut exists = false;
+    connection.iterate(format!("select name from sqlite_master where type = 'table' and name = '{}'", table), |_| { exists = true; true }).ok();
+    exists
+}
+
+/// Runs a `begin; ... commit;` batch, rolling it back if a statement fails.
+fn execute_or_rollback(connection: &Connection, sql: &str, path: &str) -> bool {
+    match connection.execute(sql) {
+        Ok(()) => true,
+        Err(e) => {
+            connection.execute("rollback;").ok();
+            eprintln!("Updating {} failed, nothing is changed: {}", path, e);
+            false
+        }
+    }
+}
+
 impl CardTransformer for CDB {
     fn to_string(_: &Card) -> String {
         unimplemented!()
     }
 
     fn from_string(from: &str) -> Vec<Card> {
-        CDB::from_connection(sqlite::open(from).expect("Cannot open sqlite file"))
+        CDB::open(from, Dialect::Ygopro)
     }
 }
 
@@ -99,6 +198,11 @@ fn get<S, T>(obj: &S, key: &str) -> T where T: Get<S> {
     T::get(obj, key)
 }
 
+/// Sqlite stores integers as i64, so 64-bit flags with the top bit set come back negative.
+fn get_bits<S>(obj: &S, key: &str) -> u64 where i64: Get<S> {
+    get::<S, i64>(obj, key) as u64
+}
+
 impl<S> Get<HashMap<&&str, &&str>> for S where S: FromStr, <S as FromStr>::Err: Debug {
     fn get(obj: &HashMap<&&str, &&str>, key: &str) -> S {
         obj.get(&key).unwrap().parse().unwrap()
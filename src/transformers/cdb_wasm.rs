@@ -1,15 +1,16 @@
 use crate::Database;
 use crate::constants::*;
-use crate::card::{Card, CardTransformer};
-use crate::transformers::{SQL, STR_FIELD_NAMES, CREATE_TABLE_SQL};
+use crate::card::{Card, CardTransformer, PackInfo, Provenance};
+use crate::transformers::{scope_to_ot, SQL, STR_FIELD_NAMES};
+use std::collections::HashMap;
 use js_sys::Array;
 use wasm_bindgen::prelude::*;
 
 pub struct CDB;
 
 impl CDB {
-    pub fn from_database(database: &Database) -> Vec<Card> {
-        const QUERY: &str = "select * from datas join texts where datas.id = texts.id";
+    pub fn from_database(database: &Database, dialect: Dialect) -> Vec<Card> {
+        const QUERY: &str = "select datas.rowid as _rowid, * from datas join texts where datas.id = texts.id";
         let mut cards = Vec::new();
         let values = database.select_objects(QUERY);
         for h in values {
@@ -19,23 +20,33 @@ impl CDB {
                 desc: get(&h, "desc"),
                 alias: get(&h, "alias"),
                 setcode: get(&h, "setcode"),
+                extra_setcodes: Vec::new(),
                 _type: Type::from_bits_retain(get(&h, "type")),
                 level: 0,
                 attribute: Attribute::empty(),
                 race: Race::empty(),
                 attack: 0,
                 defense: 0,
+                maximum_attack: 0,
                 lscale: 0,
                 rscale: 0,
                 link_marker: Linkmarkers::empty(),
                 ot: OT::from_bits_retain(get(&h, "ot")),
-                category: Category::from_bits_truncate(get(&h, "category")),
+                category: Category::from_bits_retain(get(&h, "category")),
                 texts: Vec::new(),
                 pack: None,
-                range: None
+                reprints: Vec::new(),
+                range: None,
+                desc_ranges: Vec::new(),
+                provenance: Provenance { row: Some(get::<f64>(&h, "_rowid") as i64), ..Provenance::new(Format::CDB) },
+                comments: Vec::new(),
+                inner_comments: Vec::new(),
+                limits: Vec::new()
             };
+            let mut level: u64 = 0;
             if card._type.contains(Type::Monster) {
-                card.level = get(&h, "level");
+                level = get(&h, "level");
+                card.level = level as u32;
                 card.attribute = Attribute::from_bits_retain(get(&h, "attribute"));
                 card.race = Race::from_bits_retain(get(&h, "race"));
                 card.attack = get(&h, "atk");
@@ -56,15 +67,41 @@ impl CDB {
                     card.texts.insert(i, s.to_string())
                 }
             }
+            SQL::read_maximum(&mut card, level);
+            if let Dialect::EDOPro = dialect {
+                let (ot, legend) = scope_to_ot(&Scope::from_bits_retain(get(&h, "ot")));
+                card.ot = ot;
+                card._type |= legend;
+            }
             card.level = card.level & 0xff;
             cards.push(card);
         }
+        // Cdbs written by other tools have no pack tables.
+        if database.select_objects("select name from sqlite_master where type = 'table' and name = 'card_packs'").length() > 0 {
+            let mut packs = HashMap::<String, (String, String)>::new();
+            for h in database.select_objects("select * from packs") {
+                packs.insert(get(&h, "prefix"), (get(&h, "pack"), get(&h, "date")));
+            }
+            let mut card_packs = HashMap::<u32, Vec<PackInfo>>::new();
+            for h in database.select_objects("select * from card_packs order by rowid") {
+                let id: u32 = get(&h, "id");
+                let pack_id: String = get(&h, "pack_id");
+                let (pack, date) = packs.get(SQL::pack_prefix(&pack_id)).cloned().unwrap_or_default();
+                let rarity = get::<String>(&h, "rarity").split("/").filter(|r| !r.is_empty()).map(|r| r.to_string()).collect();
+                card_packs.entry(id).or_default().push(PackInfo { id, pack_id, pack, rarity, date, japanese_name: get(&h, "japanese_name") });
+            }
+            for card in cards.iter_mut() {
+                let mut printings = card_packs.remove(&card.code).unwrap_or_default().into_iter();
+                card.pack = printings.next();
+                card.reprints = printings.collect();
+            }
+        }
         cards
     }
 
-    pub fn write_database(cards: &Vec<Card>, database: &Database) {
-        database.exec(&CREATE_TABLE_SQL);
-        database.exec(&cards.iter().map(|c| SQL::to_string(c)).collect::<Vec<_>>().join("\n"));
+    pub fn write_database(cards: &Vec<Card>, database: &Database, dialect: Dialect) {
+        database.exec(&SQL::create_table_sql());
+        database.exec(&cards.iter().map(|c| SQL::to_string_with(c, dialect)).collect::<Vec<_>>().join("\n"));
     }
 }
 
@@ -98,8 +135,12 @@ impl Get for u32 {
 }
 
 impl Get for u64 {
+    /// Bit fields with the top bit set, like Yokai races, are negative in sqlite, so the bits of the i64 are kept.
+    /// Only BigInt values keep the bits above 2^53.
     fn get(obj: &JsValue, key: &str) -> u64 {
-        get_raw(obj, key).as_f64().unwrap_or_default().ceil() as u64
+        let value = get_raw(obj, key);
+        let bits = if value.is_bigint() { i64::try_from(value).unwrap_or_default() } else { value.as_f64().unwrap_or_default() as i64 };
+        bits as u64
     }
 }
 
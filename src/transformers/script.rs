@@ -1,5 +1,7 @@
 use crate::card::Card;
 use crate::card::CardTransformer;
+use crate::card::Provenance;
+use crate::constants::Format;
 
 use super::Xyyz;
 
@@ -48,7 +50,12 @@ impl CardTransformer for Script {
                 text = text + trimmed_line.trim();
             }
         }
-        Xyyz::from_string(&text)
+        let mut cards = Xyyz::from_string(&text);
+        // Spans point into the description block, not into the script.
+        for card in cards.iter_mut() {
+            card.provenance = Provenance::new(Format::Script);
+        }
+        cards
     }
 }
 
@@ -95,12 +102,14 @@ mod test {
             desc: "①：抽1张卡。再选1张手卡丢弃。抽1张卡。再选1张手卡丢弃。抽1张卡。再选1张手卡丢弃。抽1张卡。再选1张手卡丢弃。抽1张卡。再选1张手卡丢弃。抽1张卡。再选1张手卡丢弃。\n②：回复100基本分。".to_string(),
             alias: 0,
             setcode: 0,
+            extra_setcodes: vec![],
             _type: crate::constants::Type::Spell,
             level: 0,
             attribute: crate::constants::Attribute::empty(),
             race: crate::constants::Race::empty(),
             attack: 0,
             defense: 0,
+            maximum_attack: 0,
             lscale: 0,
             rscale: 0,
             link_marker: crate::constants::Linkmarkers::empty(),
@@ -108,7 +117,13 @@ mod test {
             category: crate::constants::Category::empty(),
             texts: vec![],
             pack: None,
-            range: None
+            reprints: Vec::new(),
+            range: None,
+            desc_ranges: Vec::new(),
+            provenance: crate::card::Provenance::default(),
+            comments: Vec::new(),
+            inner_comments: Vec::new(),
+            limits: Vec::new()
         };
         println!("{}", Script::to_string(&card))
     }
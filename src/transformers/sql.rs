@@ -1,4 +1,10 @@
-use crate::{card::CardTransformer, constants::Type};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::card::{Card, CardTransformer, Provenance, Span};
+use crate::constants::{Dialect, Format, Scope, Type, OT};
 
 #[cfg(not(target_arch = "wasm32"))]
 use crate::transformers::CDB;
@@ -10,45 +16,166 @@ pub const CREATE_TABLE_SQL: &str = "
 CREATE TABLE IF NOT EXISTS datas(id integer primary key,ot integer,alias integer,setcode integer,type integer,atk integer,def integer,level integer,race integer,attribute integer,category integer);
 CREATE TABLE IF NOT EXISTS texts(id integer primary key,name text,desc text,str1 text,str2 text,str3 text,str4 text,str5 text,str6 text,str7 text,str8 text,str9 text,str10 text,str11 text,str12 text,str13 text,str14 text,str15 text,str16 text);
 ";
+/// Where cards are printed, which ygopro ignores. Packs are keyed by the prefix of pack IDs, `SD1-JP` of `SD1-JP001`,
+/// and a card has a row for each of its printings.
+pub const CREATE_PACKS_TABLE_SQL: &str = "
+CREATE TABLE IF NOT EXISTS packs(prefix text primary key,pack text,date text);
+CREATE TABLE IF NOT EXISTS card_packs(id integer,pack_id text,rarity text,japanese_name text,primary key(id,pack_id));
+";
 
-impl CardTransformer for SQL {
-    fn to_string(card: &crate::card::Card) -> String {
-        let level = card.level + (if card._type.contains(Type::Pendulum) { (card.lscale<<16) + (card.rscale<<24) } else {0});
+/// The `insert into datas ... values(id,` statement of a card, to find the line it was written on.
+static DATAS_INSERT_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"(?i)into\s+datas\b.*?values\s*\(\s*(\d+)").unwrap() });
+
+/// Classic OT flags and the EDOPro scope bits they map to. SC cards are OCG prints.
+static OT_SCOPES: [(OT, Scope); 11] = [
+    (OT::OCG, Scope::OCG),
+    (OT::TCG, Scope::TCG),
+    (OT::SC, Scope::OCG),
+    (OT::Custom, Scope::Custom),
+    (OT::Anime, Scope::Anime),
+    (OT::Illegal, Scope::Illegal),
+    (OT::VideoGame, Scope::VideoGame),
+    (OT::Speed, Scope::Speed),
+    (OT::Prerelease, Scope::Prerelease),
+    (OT::Rush, Scope::Rush),
+    (OT::Hidden, Scope::Hidden),
+];
+
+/// EDOPro marks Legend cards by scope instead of type.
+pub fn ot_to_scope(ot: &OT, _type: &Type) -> Scope {
+    let scope = OT_SCOPES.iter()
+        .filter(|(o, _)| ot.contains(*o))
+        .fold(Scope::empty(), |scope, (_, s)| scope | *s);
+    if _type.contains(Type::Legend) { scope | Scope::Legend } else { scope }
+}
+
+/// The OT flags of `scope`, and `Type::Legend` if it marks a Legend card.
+pub fn scope_to_ot(scope: &Scope) -> (OT, Type) {
+    let mut ot = OT::empty();
+    for flag in scope.iter() {
+        if let Some((o, _)) = OT_SCOPES.iter().find(|(_, s)| s.bits() == flag.bits()) {
+            ot |= *o;
+        }
+    }
+    (ot, if scope.contains(Scope::Legend) { Type::Legend } else { Type::empty() })
+}
+
+/// Marks a Maximum monster in `level`, with its Maximum ATK in bits 32 to 47, `?` being -2.
+///
+/// This is an encoding of this tool only, clients read the lower 32 bits of `level` and nothing of Maximum.
+const MAXIMUM_MARK: u64 = 1 << 48;
+
+impl SQL {
+    /// Reads the Maximum mark and ATK that `to_string_with` writes into `level`.
+    pub fn read_maximum(card: &mut Card, level: u64) {
+        if level & MAXIMUM_MARK != 0 {
+            card._type |= Type::Maximum;
+            card.maximum_attack = (level >> 32) as u16 as i16 as i32;
+        }
+    }
+
+    /// Both dialects share the ygopro tables, EDOPro only reads `ot` differently.
+    pub fn create_table_sql() -> String {
+        CREATE_TABLE_SQL.to_string() + CREATE_PACKS_TABLE_SQL
+    }
+
+    /// What `to_string_with` can't write of `card` in `dialect`.
+    pub fn warnings(card: &Card, dialect: Dialect) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !card.extra_setcodes.is_empty() {
+            warnings.push(format!("Card {} has more than 4 archetypes, the extra ones are dropped.", card.code));
+        }
+        if let Dialect::Ygopro = dialect {
+            if !card.ot.difference(OT::YGOPRO).is_empty() {
+                warnings.push(format!("Card {} has OT flags that ygopro doesn't know, they are dropped.", card.code));
+            }
+            if card._type.contains(Type::Legend) {
+                warnings.push(format!("Card {} is a Legend card, which ygopro databases can't mark.", card.code));
+            }
+        }
+        warnings
+    }
+
+    /// The prefix that `pack_id` shares with the other cards of its pack, the set and its region.
+    pub fn pack_prefix(pack_id: &str) -> &str {
+        pack_id.trim_end_matches(|c: char| c.is_ascii_digit())
+    }
+
+    pub fn to_string_with(card: &Card, dialect: Dialect) -> String {
+        let mut level = (card.level + (if card._type.contains(Type::Pendulum) { (card.lscale<<16) + (card.rscale<<24) } else {0})) as u64;
+        if card._type.contains(Type::Maximum) { level |= MAXIMUM_MARK | ((card.maximum_attack as i16 as u16 as u64) << 32) }
         let defense = if card._type.contains(Type::Link) { card.link_marker.bits() } else { card.defense };
+        let (ot, _type) = match dialect {
+            Dialect::Ygopro => (card.ot.intersection(OT::YGOPRO).bits() as u64, card._type.difference(Type::Maximum | Type::Legend)),
+            Dialect::EDOPro => (ot_to_scope(&card.ot, &card._type).bits(), card._type.difference(Type::Maximum | Type::Legend))
+        };
         let text_keys = STR_FIELD_NAMES[0..16].into_iter().map(|s| format!(",{}",s)).collect::<Vec<_>>().join("");
         let mut text_descs = card.texts.iter().map(|s| format!(",'{}'", s.replace("'", "''"))).collect::<Vec<_>>();
         while text_descs.len() < 16 { text_descs.push(",''".to_string()) }
-        format!("INSERT OR REPLACE INTO datas(id, ot,alias,setcode,type,atk,def,level,race,attribute,category) values({},{},{},{},{},{},{},{},{},{},{});\n",
-                card.code,card.ot.bits(),card.alias,card.setcode,card._type.bits(),card.attack,defense,level,card.race.bits(),card.attribute.bits(),0)+
+        let mut sql = format!("INSERT OR REPLACE INTO datas(id, ot,alias,setcode,type,atk,def,level,race,attribute,category) values({},{},{},{},{},{},{},{},{},{},{});\n",
+                card.code,ot,card.alias,card.setcode as i64,_type.bits() as i64,card.attack,defense,level as i64,card.race.bits() as i64,card.attribute.bits(),card.category.bits() as i64)+
         &format!("INSERT OR REPLACE INTO texts(id,name,desc{}) values({},'{}','{}'{});",
                 text_keys,card.code,card.name.replace("'", "''"),card.desc.replace("'", "''"),
-                text_descs.join(""))
+                text_descs.join(""));
+        for pack in card.printings().filter(|p| !p.pack_id.is_empty()) {
+            sql += &format!("\nINSERT OR REPLACE INTO card_packs(id,pack_id,rarity,japanese_name) values({},'{}','{}','{}');",
+                card.code, pack.pack_id.replace("'", "''"), pack.rarity.join("/").replace("'", "''"), pack.japanese_name.replace("'", "''"));
+            // The first card naming or dating a pack decides, see `packs::conflicts`.
+            if !pack.pack.is_empty() || !pack.date.is_empty() {
+                sql += &format!("\nINSERT INTO packs(prefix,pack,date) values('{}','{}','{}') ON CONFLICT(prefix) DO UPDATE SET \
+                    pack = CASE WHEN packs.pack = '' THEN excluded.pack ELSE packs.pack END, date = CASE WHEN packs.date = '' THEN excluded.date ELSE packs.date END;",
+                    SQL::pack_prefix(&pack.pack_id).replace("'", "''"), pack.pack.replace("'", "''"), pack.date.replace("'", "''"));
+            }
+        }
+        sql
     }
 
-    fn from_string(str: &str) -> Vec<crate::card::Card> {
+    pub fn from_string_with(str: &str, dialect: Dialect) -> Vec<Card> {
         #[cfg(target_arch = "wasm32")]
         unimplemented!("Sqlite is disabled.");
         #[cfg(not(target_arch = "wasm32"))]
         {
             let connection = sqlite::open(":memory:").expect("Cannot open sqlite memory instance");
-            connection.execute(CREATE_TABLE_SQL).expect("create table failed");
+            connection.execute(SQL::create_table_sql()).expect("create table failed");
             // for (n,line) in str.split("\n").into_iter().enumerate() {
             //     if let Err(e) = connection.execute(line) {
             //         println!("Failed to execute on line {} '{}': {}", n, line, e)
             //     }
             // }
             connection.execute(str).expect("execute sql failed");
-            CDB::from_connection(connection)
+            let lines = str.split("\n").enumerate()
+                .filter_map(|(n, line)| DATAS_INSERT_REGEX.captures(line).map(|groups| (groups[1].parse::<u32>().unwrap_or_default(), (n + 1, line.chars().count()))))
+                .collect::<HashMap<_, _>>();
+            let mut cards = CDB::from_connection(connection, dialect);
+            for card in cards.iter_mut() {
+                card.provenance = Provenance {
+                    span: lines.get(&card.code).map(|(line, length)| Span { start_line: *line, start_column: 1, end_line: *line, end_column: *length }),
+                    ..Provenance::new(Format::SQL)
+                };
+            }
+            cards
         }
     }
 }
 
+impl CardTransformer for SQL {
+    fn to_string(card: &Card) -> String {
+        SQL::to_string_with(card, Dialect::Ygopro)
+    }
+
+    fn from_string(str: &str) -> Vec<Card> {
+        SQL::from_string_with(str, Dialect::Ygopro)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::fs::read_to_string;
     use std::path::Path;
 
-    use crate::card::CardTransformer;
+    use crate::card::{Card, CardTransformer};
+    use crate::constants::{Dialect, Race, Scope, Type, OT};
+    use crate::packs;
     use crate::transformers::*;
 
     #[test]
@@ -61,6 +188,61 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_wide_race() {
+        let mut card = Card::new();
+        card.code = 10000;
+        card._type = Type::Monster | Type::Effect;
+        card.race = Race::Yokai | Race::Charisma;
+        card.setcode = 0xffff000000000001;
+        let cards = SQL::from_string(&SQL::to_string(&card));
+        assert_eq!(cards[0].race.bits(), card.race.bits());
+        assert_eq!(cards[0].setcode, card.setcode);
+    }
+
+    #[test]
+    fn test_edopro_dialect() {
+        let mut card = Card::new();
+        card.code = 10000;
+        card._type = Type::Spell;
+        card.ot = OT::Custom | OT::Rush;
+        card.set_setcodes(&[1, 2, 3, 4, 5]);
+        let sql = SQL::to_string_with(&card, Dialect::EDOPro);
+        assert!(sql.contains(&format!("values(10000,{},", (Scope::Custom | Scope::Rush).bits())));
+        let cards = SQL::from_string_with(&sql, Dialect::EDOPro);
+        assert_eq!(cards[0].ot.bits(), card.ot.bits());
+        assert_eq!(cards[0].setcodes(), vec![1, 2, 3, 4]);
+        assert_eq!(SQL::warnings(&card, Dialect::EDOPro).len(), 1);
+        assert!(SQL::to_string(&card).contains("values(10000,4,"));
+        assert_eq!(SQL::warnings(&card, Dialect::Ygopro).len(), 2);
+        card.ot = OT::all();
+        let cards = SQL::from_string(&SQL::to_string(&card));
+        assert_eq!(cards[0].ot.bits(), OT::YGOPRO.bits());
+    }
+
+    #[test]
+    fn test_packs() {
+        let text = "[INFO-JP000 QCSER/SR 2024-04-27] 骄傲与灵魂之龙(100000000) 暗 8星 龙/特殊召唤 2500 2500\n不能通常召唤。\n\n[INFO-JP001 N] 原质(100000001) 通常魔法\n抽卡。";
+        let mut cards = Xyyz::from_string(text);
+        assert_eq!(cards[0].pack.as_ref().map(|p| (p.rarity.clone(), p.date.as_str())), Some((vec!["QCSER".to_string(), "SR".to_string()], "2024-04-27")));
+        assert_eq!(Xyyz::merge_string(cards.iter()), text);
+        cards[0].pack.as_mut().unwrap().pack = "INFINITE FORBIDDEN".to_string();
+        let parsed = SQL::from_string(&cards.iter().map(|c| SQL::to_string(c)).collect::<Vec<_>>().join("\n"));
+        let pack = parsed[1].pack.as_ref().unwrap();
+        assert_eq!((pack.pack_id.as_str(), pack.pack.as_str(), pack.date.as_str()), ("INFO-JP001", "INFINITE FORBIDDEN", "2024-04-27"));
+        assert_eq!(pack.rarity, vec!["N"]);
+
+        // Reprints keep a row each, and the first card naming a pack decides its name.
+        cards[1].reprints = Xyyz::from_string("[SD1-JP003 N 2002-03-21 《STRUCTURE DECK》] 原质(100000001) 通常魔法")[0].pack.clone().into_iter().collect();
+        cards[1].pack.as_mut().unwrap().pack = "OTHER".to_string();
+        let parsed = SQL::from_string(&cards.iter().map(|c| SQL::to_string(c)).collect::<Vec<_>>().join("\n"));
+        assert_eq!(parsed[1].pack.as_ref().map(|p| p.pack.as_str()), Some("INFINITE FORBIDDEN"));
+        assert_eq!(parsed[1].reprints.iter().map(|p| (p.pack_id.as_str(), p.pack.as_str(), p.date.as_str())).collect::<Vec<_>>(), vec![("SD1-JP003", "STRUCTURE DECK", "2002-03-21")]);
+        assert_eq!(packs::conflicts(&cards), vec!["Pack INFO-JP is named both INFINITE FORBIDDEN and OTHER, INFINITE FORBIDDEN is kept."]);
+        assert_eq!(Xyyz::to_string(&parsed[1]), "[INFO-JP001 N 2024-04-27 《INFINITE FORBIDDEN》] 原质(100000001) 通常魔法\n抽卡。\n卡包：[SD1-JP003 N 2002-03-21 《STRUCTURE DECK》]");
+        assert_eq!(Xyyz::from_string(&Xyyz::to_string(&parsed[1]))[0].reprints[0].pack, "STRUCTURE DECK");
+    }
+
     #[test]
     fn test_parse() {
         let path_sql = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/transformers/test_data/xyyz.txt");
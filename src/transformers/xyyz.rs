@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::BitOr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
 
 use arc_swap::ArcSwap;
@@ -8,7 +8,9 @@ use bitflags::Flags;
 use phf::phf_map;
 use regex::Regex;
 
-use crate::card::{Card, CardTransformer, PackInfo};
+use crate::card::{Card, CardTransformer, Limit, PackInfo, Provenance, Span};
+use crate::cst;
+use crate::lflist;
 use crate::constants::*;
 
 
@@ -34,66 +36,78 @@ pub static ATTRIBUTE_NAMES: phf::Map<u32, &'static str> = phf_map! {
     64u32 => "神"
 };
 
-pub static RACE_NAMES: phf::Map<u32, &'static str> = phf_map! {
-    0u32 => "无种族",
-    1u32 => "战士",
-    2u32 => "魔法使",
-    4u32 => "天使",
-    8u32 => "恶魔",
-    16u32 => "不死",
-    32u32 => "机械",
-    64u32 => "水",
-    128u32 => "炎",
-    256u32 => "岩石",
-    512u32 => "兽战士",
-    1024u32 => "植物",
-    2048u32 => "昆虫",
-    4096u32 => "雷",
-    8192u32 => "龙",
-    16384u32 => "兽",
-    32768u32 => "兽战士",
-    65536u32 => "恐龙",
-    131072u32 => "鱼",
-    262144u32 => "海龙",
-    524288u32 => "爬行类",
-    1048576u32 => "念动力",
-    2097152u32 => "神",
-    4194304u32 => "创世神",
-    8388608u32 => "幻龙",
-    16777216u32 => "电子界",
-    33554432u32 => "幻想魔",
+pub static RACE_NAMES: phf::Map<u64, &'static str> = phf_map! {
+    0u64 => "无种族",
+    1u64 => "战士",
+    2u64 => "魔法使",
+    4u64 => "天使",
+    8u64 => "恶魔",
+    16u64 => "不死",
+    32u64 => "机械",
+    64u64 => "水",
+    128u64 => "炎",
+    256u64 => "岩石",
+    512u64 => "鸟兽",
+    1024u64 => "植物",
+    2048u64 => "昆虫",
+    4096u64 => "雷",
+    8192u64 => "龙",
+    16384u64 => "兽",
+    32768u64 => "兽战士",
+    65536u64 => "恐龙",
+    131072u64 => "鱼",
+    262144u64 => "海龙",
+    524288u64 => "爬行类",
+    1048576u64 => "念动力",
+    2097152u64 => "神",
+    4194304u64 => "创世神",
+    8388608u64 => "幻龙",
+    16777216u64 => "电子界",
+    33554432u64 => "幻想魔",
+    67108864u64 => "电子人",
+    134217728u64 => "魔导骑士",
+    268435456u64 => "多头龙",
+    536870912u64 => "欧米茄念动力",
+    1073741824u64 => "天界战士",
+    2147483648u64 => "银河",
+    0x4000000000000000u64 => "妖怪",
+    0x8000000000000000u64 => "魅惑",
 };
 
-pub static TYPE_NAMES: phf::Map<u32, &'static str> = phf_map! {
-    // 0u32 => "通常",
-    1u32 => "怪兽",
-    2u32 => "魔法",
-    4u32 => "陷阱",
-    16u32 => "通常",
-    32u32 => "效果",
-    64u32 => "融合",
-    128u32 => "仪式",
-    256u32 => "陷阱怪兽",
-    512u32 => "灵魂",
-    1024u32 => "同盟",
-    2048u32 => "二重",
-    4096u32 => "调整",
-    8192u32 => "同调",
-    16384u32 => "衍生物",
-    65536u32 => "速攻",
-    131072u32 => "永续",
-    262144u32 => "装备",
-    524288u32 => "场地",
-    1048576u32 => "反击",
-    2097152u32 => "反转",
-    4194304u32 => "卡通",
-    8388608u32 => "超量",
-    16777216u32 => "灵摆",
-    33554432u32 => "特殊召唤",
-    67108864u32 => "连接",
+pub static TYPE_NAMES: phf::Map<u64, &'static str> = phf_map! {
+    // 0u64 => "通常",
+    1u64 => "怪兽",
+    2u64 => "魔法",
+    4u64 => "陷阱",
+    16u64 => "通常",
+    32u64 => "效果",
+    64u64 => "融合",
+    128u64 => "仪式",
+    256u64 => "陷阱怪兽",
+    512u64 => "灵魂",
+    1024u64 => "同盟",
+    2048u64 => "二重",
+    4096u64 => "调整",
+    8192u64 => "同调",
+    16384u64 => "衍生物",
+    65536u64 => "速攻",
+    131072u64 => "永续",
+    262144u64 => "装备",
+    524288u64 => "场地",
+    1048576u64 => "反击",
+    2097152u64 => "反转",
+    4194304u64 => "卡通",
+    8388608u64 => "超量",
+    16777216u64 => "灵摆",
+    33554432u64 => "特殊召唤",
+    67108864u64 => "连接",
+    134217728u64 => "技能",
+    268435456u64 => "行动",
+    536870912u64 => "极大",
+    1073741824u64 => "传说",
 };
-static EX_NONEFFECT_TYPE_NAME: phf::Map<u32, &'static str> = phf_map! {
-    16u32 => "非效果"
+static EX_NONEFFECT_TYPE_NAME: phf::Map<u64, &'static str> = phf_map! {
+    16u64 => "非效果"
 };
 
 pub static LINKMARKERS_NAMES: phf::Map<i32, &'static str> = phf_map! {
@@ -115,6 +129,13 @@ pub static OT_NAMES: phf::Map<u32, &'static str> = phf_map! {
     4u32 => "Custom",
     11u32 => "SC",
     8u32 => "SCONLY",
+    16u32 => "Anime",
+    32u32 => "Illegal",
+    64u32 => "VideoGame",
+    128u32 => "Speed",
+    256u32 => "Prerelease",
+    512u32 => "Rush",
+    2048u32 => "Hidden",
     1024u32 => "Draft"
 };
 
@@ -178,7 +199,7 @@ fn join_from_phf_map<'a, K: Copy+Flags+BitOr<Output = K>>(
         if let Some(b) = search_in_phf_map(m, s) {
             z = z | K::from_bits_truncate(b);
         } else {
-            println!("Cannot recognize attribute {}", s);
+            eprintln!("Cannot recognize attribute {}", s);
         }
     }
     z
@@ -196,7 +217,7 @@ fn join_from_phf_map_dual<'a, K: Copy+Flags+BitOr<Output = K>>(
         } else if let Some(b) = search_in_phf_map(n, s) {
             z = z | K::from_bits_truncate(b);
         } else {
-            println!("Cannot recognize attribute {}", s);
+            eprintln!("Cannot recognize attribute {}", s);
         }
     }
     z
@@ -231,7 +252,236 @@ fn add_string_conf_to_hashmap(setnames: &mut HashMap<u16, String>, str: impl AsR
 
 pub struct Xyyz;
 
+/// The cards of an xyyz source and the comments after the last one, which belong to no card.
+#[derive(Debug, Clone, Default)]
+pub struct XyyzDocument {
+    pub cards: Vec<Card>,
+    pub trailing_comments: Vec<String>
+}
+
+impl XyyzDocument {
+    /// Adds `card`, putting the comments that ended the document so far before it.
+    fn push(&mut self, mut card: Card) {
+        if !self.trailing_comments.is_empty() {
+            card.comments.splice(0..0, std::mem::take(&mut self.trailing_comments));
+        }
+        self.cards.push(card)
+    }
+
+    /// Adds the cards of `other` after these, the comments that ended this document going before them.
+    pub fn append(&mut self, other: XyyzDocument) {
+        other.cards.into_iter().for_each(|card| self.push(card));
+        self.trailing_comments.extend(other.trailing_comments);
+    }
+
+    pub fn to_string_with(&self, style: HeaderStyle) -> String {
+        self.cards.iter().map(|c| Xyyz::to_string_with(c, style))
+            .chain((!self.trailing_comments.is_empty()).then(|| self.trailing_comments.join("\n")))
+            .collect::<Vec<_>>().join("\n\n")
+    }
+}
+
 impl Xyyz {
+    /// Writes a card in xyyz, with its header in `style`.
+    ///
+    /// The original style follows the Chinese name with the code, and cards without a pack ID keep the code style.
+    pub fn to_string_with(card: &Card, style: HeaderStyle) -> String {
+        let mut str = String::new();
+        for comment in card.comments.iter() {
+            str += comment;
+            str += "\n";
+        }
+        let alias_text = if card.alias > 0 { format!("=>{}", card.alias) } else { String::new() };
+        let mut code_text = if card.code > 0 { format!("({}{})", card.code, alias_text) }else { String::new() };
+        let mut name = card.name.clone();
+        match card.pack.as_ref().filter(|p| !p.pack_id.is_empty()) {
+            Some(pack) if matches!(style, HeaderStyle::Original) => {
+                let japanese_name = if pack.japanese_name.is_empty() { &card.name } else { &pack.japanese_name };
+                name = format!("{}({}){}", pack.pack_id, pack.rarity.join("/"), japanese_name);
+                code_text = format!("({}){}", card.name, code_text);
+            }
+            Some(pack) => str += &format!("[{}] ", Self::format_pack(pack)),
+            None => {}
+        }
+        if card._type.contains(Type::Monster) {
+            str += &format!("{}{} {} {} {}{} {} {}", 
+                name, 
+                code_text,
+                Self::format_attribute(&card.attribute), 
+                Self::format_level(card), 
+                Self::format_race(&card.race), 
+                Self::format_subtype(&card._type), 
+                Self::format_number(card.attack),
+                if card._type.contains(Type::Link) { String::new() } else { Self::format_number(card.defense) }
+            );
+            if card._type.contains(Type::Link) {
+                str += &Self::format_linkmarkers(&card.link_marker)
+            }
+            if card._type.contains(Type::Maximum) {
+                str += &format!(" 极大攻击力{}", Self::format_number(card.maximum_attack))
+            }
+        } else {
+            str += &format!("{}{} {}", name, code_text, Self::format_type(&card._type))
+        };
+        if card.ot.bits() != (OT::OCG | OT::TCG).bits() {
+            str += " (";
+            str += &Self::format_ot(&card.ot);
+            str += ")"
+        }
+        let mut body = Vec::new();
+        if let Some(setnames) = Self::format_setcode(card) {
+            if setnames.len() > 0 {
+                body.push(format!("系列：{}", setnames));
+            }
+        }
+        if !card.desc.is_empty() {
+            body.extend(card.desc.split("\n").map(|l| l.to_string()));
+        }
+        if ! card.category.is_empty() {
+            body.push(format!("效果分类：{}", Self::format_category(&card.category)));
+        }
+        if card.texts.len() > 0 {
+            body.push(format!("提示文本：{}", card.texts.join("、")));
+        };
+        if !card.reprints.is_empty() {
+            body.push(format!("卡包：{}", card.reprints.iter().map(|p| format!("[{}]", Self::format_pack(p))).collect::<Vec<_>>().join("、")));
+        }
+        if !card.limits.is_empty() {
+            body.push(format!("禁限：{}", card.limits.iter().map(|l| format!("{} {}", l.list, lflist::format_count(l.count))).collect::<Vec<_>>().join("、")));
+        }
+        // Comments inside the card go back after as many lines as they were written after.
+        for (i, line) in body.iter().enumerate() {
+            for (_, comment) in card.inner_comments.iter().filter(|(n, _)| *n == i) {
+                str += &format!("\n{}", comment);
+            }
+            str += &format!("\n{}", line);
+        }
+        for (_, comment) in card.inner_comments.iter().filter(|(n, _)| *n >= body.len()) {
+            str += &format!("\n{}", comment);
+        }
+        str
+    }
+
+    /// `[SD1-JP001 SR/UR 2002-03-21 《STRUCTURE DECK》]`, the pack ID optionally followed by rarities, a release date and the pack name.
+    fn parse_pack(text: &str, id: u32) -> PackInfo {
+        let text = text.trim().trim_start_matches('[').trim_end_matches(']');
+        let name = PACK_NAME_REGEX.captures(text).map(|groups| groups[1].trim().to_string()).unwrap_or_default();
+        let text = PACK_NAME_REGEX.replace(text, " ");
+        let mut words = text.split_whitespace();
+        let mut pack = PackInfo { id, pack_id: words.next().unwrap_or_default().to_string(), pack: name, rarity: vec![], date: String::new(), japanese_name: String::new() };
+        for word in words {
+            if DATE_REGEX.is_match(word) { pack.date = word.to_string() }
+            else { pack.rarity.extend(word.split("/").filter(|r| !r.is_empty()).map(|r| r.to_string())) }
+        }
+        pack
+    }
+
+    fn format_pack(pack: &PackInfo) -> String {
+        let rarity = pack.rarity.join("/");
+        let name = if pack.pack.is_empty() { String::new() } else { format!("《{}》", pack.pack) };
+        [pack.pack_id.as_str(), rarity.as_str(), pack.date.as_str(), name.as_str()].iter().filter(|w| !w.is_empty()).cloned().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Orders `cards` like the cards of an xyyz `layout` and takes their comments and section headers, matching by code.
+    /// 
+    /// Cards missing from the layout keep their order after the others.
+    pub fn apply_layout(cards: &mut [Card], layout: &[Card]) {
+        let position = |card: &Card| layout.iter().position(|l| if card.code > 0 { l.code == card.code } else { l.name == card.name });
+        cards.sort_by_key(|card| position(card).unwrap_or(usize::MAX));
+        for card in cards.iter_mut() {
+            if let Some(i) = position(card) {
+                if card.comments.is_empty() { card.comments = layout[i].comments.clone() }
+                if card.inner_comments.is_empty() { card.inner_comments = layout[i].inner_comments.clone() }
+            }
+        }
+    }
+
+    /// Reads an xyyz file along with the files it `#include`, resolved relative to the including file.
+    /// 
+    /// Included cards take the place of the directive, and every file is read only once.
+    pub fn open(path: &str) -> Vec<Card> {
+        Self::open_document(path).cards
+    }
+
+    /// Like `open`, keeping the comments after the last card. Those of an included file go before the card after its directive.
+    pub fn open_document(path: &str) -> XyyzDocument {
+        Self::open_included(Path::new(path), &mut Vec::new(), &mut HashSet::new())
+    }
+
+    /// The `#include` lines of an xyyz document, as the char offset they start at and the path they name as written.
+    pub fn includes(text: &str) -> Vec<(usize, String)> {
+        let mut index = 0;
+        let mut includes = Vec::new();
+        for line in text.split("\n") {
+            if let Some(groups) = INCLUDE_REGEX.captures(line) { includes.push((index, groups[1].to_string())) }
+            index += line.chars().count() + 1;
+        }
+        includes
+    }
+
+    fn open_included(path: &Path, stack: &mut Vec<PathBuf>, visited: &mut HashSet<PathBuf>) -> XyyzDocument {
+        let key = path.canonicalize().unwrap_or(path.to_path_buf());
+        if stack.contains(&key) {
+            eprintln!("Include cycle: {} -> {}", stack.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(" -> "), key.display());
+            return XyyzDocument::default()
+        }
+        if !visited.insert(key.clone()) {
+            eprintln!("{} is included more than once, only its first include is read.", key.display());
+            return XyyzDocument::default()
+        }
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if !stack.is_empty() => { eprintln!("Can't read included file {}: {}", path.display(), e); return XyyzDocument::default() }
+            Err(e) => panic!("Read file {} failed: {}", path.display(), e)
+        };
+        let XyyzDocument { mut cards, mut trailing_comments } = Self::parse_document(&text);
+        // The included cards stand in for the directives, which would include them again once written back.
+        for card in cards.iter_mut() {
+            card.comments.retain(|c| !INCLUDE_REGEX.is_match(c));
+            card.inner_comments.retain(|(_, c)| !INCLUDE_REGEX.is_match(c));
+        }
+        trailing_comments.retain(|c| !INCLUDE_REGEX.is_match(c));
+        let mut cards = cards.into_iter().peekable();
+        stack.push(key);
+        let mut result = XyyzDocument::default();
+        for (index, include) in Self::includes(&text) {
+            while let Some(card) = cards.next_if(|c| c.range.is_some_and(|r| r.start < index)) { result.push(card) }
+            let included = path.parent().unwrap_or(Path::new("")).join(include);
+            result.append(Self::open_included(&included, stack, visited));
+        }
+        cards.for_each(|card| result.push(card));
+        result.trailing_comments.append(&mut trailing_comments);
+        stack.pop();
+        let file = path.to_string_lossy().to_string();
+        for card in result.cards.iter_mut().filter(|c| c.provenance.path.is_none()) {
+            card.provenance.path = Some(file.clone());
+        }
+        result
+    }
+
+    /// Rewrites an xyyz document in canonical form, keeping comments, section headers and line endings.
+    /// 
+    /// Cards naming archetypes missing from strings.conf are kept as written, so those names are not lost.
+    pub fn format_source(source: &str) -> String {
+        let XyyzDocument { cards, trailing_comments } = Self::parse_document(source);
+        if cards.is_empty() { return source.to_string() }
+        let document = cst::Document::parse(source);
+        let blocks = cards.iter().zip(document.cards()).map(|(card, node)| {
+            let original = node.lines.iter().map(|l| l.text()).filter(|l| !l.trim().is_empty()).collect::<Vec<_>>();
+            if !original.iter().any(|l| Self::has_unknown_setname(l)) { return Self::to_string(card) }
+            card.comments.iter().cloned().chain(original).collect::<Vec<_>>().join("\n")
+        }).chain((!trailing_comments.is_empty()).then(|| trailing_comments.join("\n"))).collect::<Vec<_>>();
+        let formatted = blocks.join("\n\n") + "\n";
+        // Files written with CRLF keep it.
+        if source.contains("\r\n") { formatted.replace("\n", "\r\n") } else { formatted }
+    }
+
+    fn has_unknown_setname(line: &str) -> bool {
+        let Some(sets) = line.strip_prefix("系列：").or(line.strip_prefix("系列字段：")) else { return false };
+        let setnames = SET_NAMES.load();
+        sets.split("、").map(|s| s.trim()).any(|s| !s.starts_with("0x") && !setnames.values().any(|v| v == s))
+    }
+
     pub fn format_level(this: &Card) -> String {
         if this._type.contains(Type::Xyz) { format!("{}阶", this.level) }
         else if this._type.contains(Type::Link) { format!("LINK-{}", this.link_marker.iter().count()) }
@@ -251,7 +501,7 @@ impl Xyyz {
             card.level = str.trim_start_matches("LINK-").trim().parse().unwrap_or(0)
         }
         else {
-            println!("Can't recognize level {}", str)
+            eprintln!("Can't recognize level {}", str)
         }
     }
 
@@ -274,18 +524,17 @@ impl Xyyz {
 
     pub fn format_setcode(this: &Card) -> Option<String> {
         let setnames = SET_NAMES.load();
-        Some([this.setcode & 0xffff, (this.setcode & 0xffff0000) >> 16, (this.setcode & 0xffff00000000) >> 32, (this.setcode & 0xffff000000000000) >> 48]
+        Some(this.setcodes()
             .into_iter()
-            .filter(|set| *set > 0)
-            .map(|set| setnames.get(&(set as u16)).unwrap_or(&format!("0x{:X}", set)).clone())
+            .map(|set| setnames.get(&set).unwrap_or(&format!("0x{:X}", set)).clone())
             .collect::<Vec<_>>()
             .join("、"))
     }
 
-    fn get_setcode(str: &str) -> u64 {
+    fn get_setcode(str: &str) -> Vec<u16> {
         let setnames = SET_NAMES.load();
         
-        let mut setcodes: u64 = 0;
+        let mut setcodes = Vec::new();
         for setname in str.split("、") {
             let setname = setname.trim();
             let setcode = if setname.starts_with("0x") {
@@ -294,10 +543,10 @@ impl Xyyz {
                 setnames.iter().find(|(_, v)| v == &&setname).map(|(k, _)| *k)
             };
             if let Some(s) = setcode {
-                setcodes = setcodes.checked_shl(16).unwrap() + s as u64;
+                setcodes.push(s);
             }
             else {
-                println!("Can't recoginize set {}. ", setname)
+                eprintln!("Can't recoginize set {}. ", setname)
             }
         }
         setcodes
@@ -314,21 +563,33 @@ impl Xyyz {
 
     pub fn format_race(this: &Race) -> String {
         if this.is_empty() { return RACE_NAMES[&0].to_string() }
-        this.iter().map(|a| RACE_NAMES[&a.bits()]).collect::<Vec<_>>().join("/")
+        // Races this tool has no name for are written as hex, like categories.
+        this.iter().map(|a| RACE_NAMES.get(&a.bits()).map(|n| n.to_string()).unwrap_or(format!("0x{:X}", a.bits()))).collect::<Vec<_>>().join("/")
     }
 
     fn get_race(value: &str) -> Race {
-        join_from_phf_map(&RACE_NAMES, value.split("/"))
+        let mut race = Race::empty();
+        for name in value.split("/") {
+            if let Some(bits) = name.strip_prefix("0x").and_then(|n| u64::from_str_radix(n, 16).ok()) {
+                race |= Race::from_bits_retain(bits);
+            } else {
+                race |= join_from_phf_map(&RACE_NAMES, std::iter::once(name));
+            }
+        }
+        race
     }
 
     pub fn format_type(this: &Type) -> String {
+        if this.contains(Type::Legend) { return format!("{}{}", TYPE_NAMES[&Type::Legend.bits()], Self::format_type(&this.difference(Type::Legend))) }
         if this.contains(Type::Monster) { }
         if this.contains(Type::Spell) { return format!("{}{}", TYPE_NAMES.get(&(this.bits() - &Type::Spell.bits())).unwrap_or(&TYPE_NAMES[&Type::Normal.bits()]), TYPE_NAMES[&Type::Spell.bits()]) }
         if this.contains(Type::Trap) { return format!("{}{}", TYPE_NAMES.get(&(this.bits() - &Type::Trap.bits())).unwrap_or(&TYPE_NAMES[&Type::Normal.bits()]), TYPE_NAMES[&Type::Trap.bits()]) }
+        if this.contains(Type::Skill) { return TYPE_NAMES[&Type::Skill.bits()].to_string() }
         String::new()
     }
 
     fn get_type(str: &str) -> Type {
+        if let Some(rest) = str.strip_prefix(TYPE_NAMES[&Type::Legend.bits()]) { return Self::get_type(rest) | Type::Legend }
         let mut v = 0;
         let leading_length = TYPE_NAMES[&Type::Normal.bits()].len();
         let all_length = leading_length + TYPE_NAMES[&Type::Monster.bits()].len();
@@ -346,7 +607,7 @@ impl Xyyz {
         let mut z1 = this_intersected.iter().map(|t| TYPE_NAMES[&t.bits()]).collect::<Vec<_>>();
         if this.intersects(ex_type) && !this.contains(Type::Effect) { z1.push(EX_NONEFFECT_TYPE_NAME[&16]); } // Add 'non-effect' label for ex monsters.
         
-        let sub_type = Type::Flip | Type::Token | Type::Spirit | Type::Union | Type::Toon | Type::Dual | Type::Tuner;
+        let sub_type = Type::Flip | Type::Token | Type::Spirit | Type::Union | Type::Toon | Type::Dual | Type::Tuner | Type::Legend;
         let z2 = this.intersection(sub_type).iter().map(|t| TYPE_NAMES[&t.bits()]).collect::<Vec<_>>();
         z1.extend(z2);
         if z1.len() == 0 { String::new() }
@@ -384,11 +645,20 @@ impl Xyyz {
     }
 
     pub fn format_category(this: &Category) -> String {
-        this.iter().map(|c| CATEGORY_NAMES[&c.bits()]).collect::<Vec<_>>().join("、")
+        // Unnamed bits of newer cores come last from iter(), written as hex.
+        this.iter().map(|c| CATEGORY_NAMES.get(&c.bits()).map(|n| n.to_string()).unwrap_or(format!("0x{:X}", c.bits()))).collect::<Vec<_>>().join("、")
     }
 
     fn get_category(value: &str) -> Category {
-        join_from_phf_map(&CATEGORY_NAMES, value.split("、").map(|v| v.trim()))
+        let mut category = Category::empty();
+        for name in value.split("、").map(|v| v.trim()) {
+            if let Some(bits) = name.strip_prefix("0x").and_then(|n| u64::from_str_radix(n, 16).ok()) {
+                category |= Category::from_bits_retain(bits);
+            } else {
+                category |= join_from_phf_map(&CATEGORY_NAMES, std::iter::once(name));
+            }
+        }
+        category
     }
 
     fn read_part_str(part_str: &str, card: &mut Card) {
@@ -400,6 +670,7 @@ impl Xyyz {
             let atk_str = parts.get(5).unwrap();
             let def_str = parts.get(6).unwrap();
             let linkmarker_str = parts.get(7);
+            let maximum_atk_str = parts.get(8);
 
             card.attribute = Self::get_attribute(attr_str.as_str());
             Self::set_level(card, level_str.as_str());
@@ -412,6 +683,10 @@ impl Xyyz {
                 card.link_marker = Self::get_linkmarkers(marker.as_str());
                 card.defense = card.link_marker.bits();
             }
+            if let Some(maximum_atk) = maximum_atk_str {
+                card._type |= Type::Maximum;
+                card.maximum_attack = Self::get_num(maximum_atk.as_str());
+            }
         }
         else { 
             card._type = card._type | Self::get_type(part_str); 
@@ -420,81 +695,104 @@ impl Xyyz {
 
 }
 
-static PARTS_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^(.+?) (.+?) (.+?)((?:/.+?)*) (\d+|\?|∞) ?(\d+|\?|∞|(\[.+\])?)$").unwrap() });
+static INCLUDE_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^#include\s+(.+?)\s*$").unwrap() });
+
+/// The pack name in the brackets of a header, `《STRUCTURE DECK》`.
+static PACK_NAME_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"《(.*)》").unwrap() });
+
+static DATE_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^\d{4}-\d{1,2}-\d{1,2}$").unwrap() });
+
+/// `name(code=>alias) parts (ot)`, optionally led by a `[pack-id rarity date]`.
+pub(crate) static HEADER_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^(\[.+\-.+\]\s+)?(.+)\((\d+)(\s*=>\s*(\d+)\s*)?\)\s+(.+?)\s*(\((.*)\))?$").unwrap() });
+/// `pack-id(rarity)japanese name(chinese name)(code=>alias) parts (ot)`, the original XYZ format. The code is optional.
+///
+/// Lines matching it also match `HEADER_REGEX` when they have a code, so it is tried first.
+pub(crate) static ORIGINAL_HEADER_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^([0-9A-Za-z/]+-[A-Za-z]*\d+)\(([^()]*)\)(.+?)\(([^()]+)\)(\((\d+)(\s*=>\s*(\d+)\s*)?\))?\s+(.+?)\s*(\((.*)\))?$").unwrap() });
+/// Headers of cards that have no code yet.
+pub(crate) static HEADER_WEAK_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^(\[.+\-.+\]\s+)?((.+?)(\S+魔法|\S+陷阱|(?:\s(\d+|∞|\?))|(\[.\])))\s*(\((.*)\))?$").unwrap() });
+
+/// Metadata lines of a card body, which are never headers.
+pub(crate) const FIELD_PREFIXES: [&str; 6] = ["系列字段：", "系列：", "效果分类：", "提示文本：", "卡包：", "禁限："];
+
+/// `←4 【灵摆】 4→`, the scales line of a pendulum card.
+pub(crate) static PENDULUM_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^←(\d+)\s*【灵摆】\s*(\d+)→$").unwrap() });
+
+/// Decorative lines like `——————第一页——————` that group the cards after them.
+pub(crate) static SECTION_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^\s*[—－\-=＝]{2,}.*[—－\-=＝]{2,}\s*$").unwrap() });
+
+pub(crate) static PARTS_REGEX: LazyLock<Regex> = LazyLock::new(|| { Regex::new(r"^(.+?) (.+?) (.+?)((?:/.+?)*) (\d+|\?|∞) ?(\d+|\?|∞|(\[.+\])?)(?: 极大攻击力(\d+|\?|∞))?$").unwrap() });
 
 impl CardTransformer for Xyyz {
     fn to_string(card: &Card) -> String {
-        let mut str = String::new();
-        let alias_text = if card.alias > 0 { format!("=>{}", card.alias) } else { String::new() };
-        let code_text = if card.code > 0 { format!("({}{})", card.code, alias_text) }else { String::new() };
-        if card._type.contains(Type::Monster) {
-            str += &format!("{}{} {} {} {}{} {} {}", 
-                card.name, 
-                code_text,
-                Self::format_attribute(&card.attribute), 
-                Self::format_level(card), 
-                Self::format_race(&card.race), 
-                Self::format_subtype(&card._type), 
-                Self::format_number(card.attack),
-                if card._type.contains(Type::Link) { String::new() } else { Self::format_number(card.defense) }
-            );
-            if card._type.contains(Type::Link) {
-                str += &Self::format_linkmarkers(&card.link_marker)
-            }
-        } else {
-            str += &format!("{}({}{}) {}", card.name, card.code, alias_text, Self::format_type(&card._type))
-        };
-        if card.ot.bits() != (OT::OCG | OT::TCG).bits() {
-            str += " (";
-            str += &Self::format_ot(&card.ot);
-            str += ")"
-        }
-        if let Some(setnames) = Self::format_setcode(card) {
-            if setnames.len() > 0 {
-                str += &format!("\n系列：{}", setnames);
-            }
-        }
-        str += &format!("\n{}", card.desc);
-        if ! card.category.is_empty() {
-            str += &format!("\n效果分类：{}", Self::format_category(&card.category));
-        }
-        if card.texts.len() > 0 {
-            str += &format!("\n提示文本：{}", card.texts.join("、"));
-        };
-        str
+        Xyyz::to_string_with(card, HeaderStyle::Code)
     }
 
     fn from_string(str: &str) -> Vec<Card> {
+        Xyyz::parse_document(str).cards
+    }
+}
+
+impl Xyyz {
+    /// Reads the cards of an xyyz source along with the comments after the last one.
+    pub fn parse_document(str: &str) -> XyyzDocument {
         let mut cards = Vec::new();
         let mut current_card: Option<Card> = None;
         let mut current_index = 0;
-        let line_regex = Regex::new(r"^(\[.+\-.+\]\s+)?(.+)\((\d+)(\s*=>\s*(\d+)\s*)?\)\s+(.+?)\s*(\((.*)\))?$").unwrap();
-        let line_weak_regex = Regex::new(r"^(\[.+\-.+\]\s+)?((.+?)(\S+魔法|\S+陷阱|(?:\s(\d+|∞|\?))|(\[.\])))\s*(\((.*)\))?$").unwrap();
-        let pendulum_regex: Regex = Regex::new(r"^←(\d+)\s*【灵摆】\s*(\d+)→$").unwrap();
-        for line in str.split("\n") {
-            let current_line_length = line.chars().count() + 1;
-            if line.starts_with("#") { current_index += current_line_length; continue; }
+        let line_regex = &*HEADER_REGEX;
+        let line_weak_regex = &*HEADER_WEAK_REGEX;
+        let pendulum_regex = &*PENDULUM_REGEX;
+        // Comments and section headers wait here for the card they are written before.
+        let mut comments = Vec::new();
+        // Lines read after the header of the current card.
+        let mut body_lines = 0;
+        for raw_line in str.split("\n") {
+            let current_line_length = raw_line.chars().count() + 1;
+            let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+            if line.starts_with("#") { comments.push(line.to_string()); current_index += current_line_length; continue; }
+            if SECTION_REGEX.is_match(line) {
+                comments.push(line.to_string());
+                current_index += current_line_length;
+                continue;
+            }
             if line.trim().len() == 0 {
-                if let Some(card) = current_card.as_mut() {
-                    if let Some(range) = card.range.as_mut() {
-                        range.end = current_index;
-                    }
-                }
+                if comments.last().is_some_and(|c: &String| !c.is_empty()) { comments.push(String::new()) }
                 current_index += current_line_length;
                 continue; 
             }
             
             let mut new_card = None;
-            if let Some(groups) = line_regex.captures(line) {
+            // A metadata line ending in a number would pass for a weak header.
+            if FIELD_PREFIXES.iter().any(|p| line.starts_with(p)) {}
+            else if let Some(groups) = ORIGINAL_HEADER_REGEX.captures(line) {
+                let mut card = Card::new();
+                card.code = groups.get(6).map_or(0, |u| u.as_str().parse().unwrap_or_default());
+                card.alias = groups.get(8).map_or(0, |u| u.as_str().parse().unwrap_or_default());
+                card.pack = Some(PackInfo {
+                    id: card.code,
+                    pack_id: groups.get(1).unwrap().as_str().to_string(),
+                    pack: String::new(),
+                    rarity: groups.get(2).unwrap().as_str().split("/").filter(|r| !r.is_empty()).map(|r| r.to_string()).collect(),
+                    date: String::new(),
+                    japanese_name: groups.get(3).unwrap().as_str().to_string()
+                });
+                card.name = groups.get(4).unwrap().as_str().to_string();
+                card.range = Some((current_index..current_index + line.chars().count()).into());
+                if let Some(ot) = groups.get(11) {
+                    card.ot = Self::get_ot(ot.as_str());
+                }
+                Xyyz::read_part_str(groups.get(9).unwrap().as_str(), &mut card);
+                new_card = Some(card)
+            }
+            else if let Some(groups) = line_regex.captures(line) {
                 let code: u32 = groups.get(3).unwrap().as_str().parse().unwrap_or_default();
                 let mut card = Card::new();
                 card.code = code;
-                card.pack = groups.get(1).map(|u| PackInfo { id: code, pack_id: u.as_str()[1..u.as_str().len()-1].to_string(), pack: String::new(), rarity: vec![], date: String::new() });
+                card.pack = groups.get(1).map(|u| Self::parse_pack(u.as_str(), code));
                 card.name = groups.get(2).unwrap().as_str().to_string();
                 if let Some(u) = groups.get(5) { 
                     card.alias = u.as_str().parse().unwrap() 
                 }
-                card.range = Some((current_index..current_index).into()); 
+                card.range = Some((current_index..current_index + line.chars().count()).into()); 
                 if let Some(ot) = groups.get(8) {
                     card.ot = Self::get_ot(ot.as_str());
                 }
@@ -515,23 +813,31 @@ impl CardTransformer for Xyyz {
                     None => 0
                 };
                 let mut card = Card::new();
-                card.pack = groups.get(1).map(|u| PackInfo { id: 0, pack_id: u.as_str()[1..u.as_str().len()-1].to_string(), pack: String::new(), rarity: vec![], date: String::new() });
+                card.pack = groups.get(1).map(|u| Self::parse_pack(u.as_str(), 0));
                 card.name = mix_str[0..pos].to_string();
-                card.range = Some((current_index..current_index).into()); 
+                card.range = Some((current_index..current_index + line.chars().count()).into()); 
                 if let Some(ot) = groups.get(8) {
                     card.ot = Self::get_ot(ot.as_str());
                 }
                 Xyyz::read_part_str(&mix_str[pos..].trim(), &mut card);
                 new_card = Some(card);
             }
-            if let Some(card) = new_card {
-                if let Some(mut card) = current_card { 
-                    set_card_range(&mut card, current_index-1); 
+            if let Some(mut card) = new_card {
+                card.comments = std::mem::take(&mut comments);
+                body_lines = 0;
+                if let Some(card) = current_card { 
                     cards.push(card); 
                 }
                 current_card = Some(card);
             }
             else if let Some(c) = current_card.as_mut() {
+                if let Some(range) = c.range.as_mut() {
+                    range.end = current_index + line.chars().count();
+                }
+                // Comments followed by more of the card stay inside it.
+                while comments.last().is_some_and(|c| c.is_empty()) { comments.pop(); }
+                c.inner_comments.extend(comments.drain(..).map(|comment| (body_lines, comment)));
+                body_lines += 1;
                 if c._type.contains(Type::Pendulum) {
                     if let Some(groups) = pendulum_regex.captures(&line) {
                         c.lscale = groups.get(1).unwrap().as_str().parse().unwrap();
@@ -539,10 +845,12 @@ impl CardTransformer for Xyyz {
                     }
                 }
                 if line.starts_with("系列字段：") {
-                    c.setcode = Self::get_setcode(line.trim_start_matches("系列字段："))
+                    c.set_setcodes(&Self::get_setcode(line.trim_start_matches("系列字段：")))
                 }
                 if line.starts_with("系列：") {
-                    c.setcode = c.setcode | Self::get_setcode(line.trim_start_matches("系列："))
+                    let mut setcodes = c.setcodes();
+                    setcodes.extend(Self::get_setcode(line.trim_start_matches("系列：")));
+                    c.set_setcodes(&setcodes)
                 }
                 else if line.starts_with("效果分类：") {
                     c.category = Self::get_category(line.trim_start_matches("效果分类："))
@@ -550,36 +858,58 @@ impl CardTransformer for Xyyz {
                 else if line.starts_with("提示文本：") {
                     c.texts = line.trim_start_matches("提示文本：").split("、").map(|t| t.to_string()).collect();
                 }
+                else if line.starts_with("卡包：") {
+                    c.reprints = line.trim_start_matches("卡包：").split("、").filter(|p| !p.trim().is_empty()).map(|p| Self::parse_pack(p, c.code)).collect();
+                }
+                else if line.starts_with("禁限：") {
+                    c.limits = line.trim_start_matches("禁限：").split("、")
+                        .filter_map(|l| l.trim().rsplit_once(" ").and_then(|(list, count)| lflist::parse_count(count).map(|count| Limit { list: list.trim().to_string(), count })))
+                        .collect();
+                }
                 else {
                     if c.desc.len() > 0 {
                         c.desc.push('\n');
                     }
                     c.desc.extend(line.chars()); 
+                    c.desc_ranges.push((current_index..current_index + line.chars().count()).into());
                 }
             }
+            else {
+                // Lines before the first card are kept along with its comments.
+                comments.push(line.to_string());
+            }
             current_index += current_line_length; 
         }
-        if let Some(mut card) = current_card {
-            set_card_range(&mut card, str.len());
+        if let Some(card) = current_card {
             cards.push(card) 
         }
-        println!("Parsed {} cards.", cards.len());
-        cards
+        let line_starts = std::iter::once(0).chain(str.split("\n").scan(0, |start, line| { *start += line.chars().count() + 1; Some(*start) })).collect::<Vec<_>>();
+        for card in cards.iter_mut() {
+            card.provenance = Provenance { span: card.range.map(|r| span_of(&line_starts, r.start, r.end)), ..Provenance::new(Format::Xyyz) };
+        }
+        while comments.last().is_some_and(|c| c.is_empty()) { comments.pop(); }
+        XyyzDocument { cards, trailing_comments: comments }
     }
 }
 
-fn set_card_range(card: &mut Card, end: usize) {
-    if let Some(range) = card.range.as_mut() {
-        if range.start >= range.end {
-            range.end = end;
-        }
-    }
+/// Converts char offsets into 1-based lines and columns, given the offset each line starts at.
+fn span_of(line_starts: &[usize], start: usize, end: usize) -> Span {
+    let position = |offset: usize| {
+        let line = line_starts.partition_point(|s| *s <= offset).clamp(1, line_starts.len() - 1);
+        let length = line_starts[line] - line_starts[line - 1] - 1;
+        (line, (offset - line_starts[line - 1] + 1).min(length.max(1)))
+    };
+    // The range end is exclusive.
+    let (start_line, start_column) = position(start);
+    let (end_line, end_column) = position(end.max(start + 1) - 1);
+    Span { start_line, start_column, end_line, end_column }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::Path;
     use crate::card::CardTransformer;
+    use crate::constants::{Category, Dialect, HeaderStyle, Race, Type};
     use crate::transformers::*;
 
     #[test]
@@ -609,6 +939,105 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_comments() {
+        let text = "# 第一弹\n原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\n起点之果。\n——————第一页——————\n\n原质贝塔(10001) 通常魔法\n抽1张卡。\n\n# 完";
+        let mut cards = Xyyz::from_string(text);
+        assert_eq!(cards[0].comments, vec!["# 第一弹"]);
+        assert_eq!(cards[0].desc, "起点之果。");
+        assert_eq!(cards[1].comments, vec!["——————第一页——————", ""]);
+        assert_eq!(Xyyz::parse_document(text).trailing_comments, vec!["# 完"]);
+        let inner = Xyyz::parse_document("原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\n起点之果。\n# 待定\n\n效果分类：0x1\n# 完");
+        assert_eq!(inner.cards[0].inner_comments, vec![(1, "# 待定".to_string())]);
+        assert_eq!(inner.to_string_with(HeaderStyle::Code), "原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\n起点之果。\n# 待定\n效果分类：魔陷破坏\n\n# 完");
+        assert_eq!(Xyyz::parse_document(text).to_string_with(HeaderStyle::Code), "# 第一弹\n原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\n起点之果。\n\n——————第一页——————\n\n原质贝塔(10001) 通常魔法\n抽1张卡。\n\n# 完");
+        // Without cards the comments are still the document.
+        let only_comments = Xyyz::parse_document("# 第一弹\n# 完\n");
+        assert!(only_comments.cards.is_empty());
+        assert_eq!(only_comments.to_string_with(HeaderStyle::Code), "# 第一弹\n# 完");
+        let layout = Xyyz::from_string(text);
+        cards.reverse();
+        cards.iter_mut().for_each(|c| c.comments.clear());
+        Xyyz::apply_layout(&mut cards, &layout);
+        assert_eq!(Xyyz::merge_string(cards.iter()), Xyyz::merge_string(layout.iter()));
+    }
+
+    #[test]
+    fn test_format_source() {
+        let text = "说明文字\n# 第一弹\n原质阿尔法(10000)  暗 3星 恶魔/通常 1000 1000\n效果分类：0x1\n起点之果。\n系列：0xFF0\n\n\n\n原质贝塔 通常魔法\n系列：不存在的系列测试\n抽1张卡。\n";
+        let formatted = Xyyz::format_source(text);
+        assert_eq!(formatted, "说明文字\n# 第一弹\n原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\n系列：0xFF0\n起点之果。\n效果分类：魔陷破坏\n\n原质贝塔 通常魔法\n系列：不存在的系列测试\n抽1张卡。\n");
+        assert_eq!(Xyyz::format_source(&formatted), formatted);
+        let text = "原质阿尔法(10000) 暗 3星 恶魔/通常 1000 1000\r\n起点之果。\r\n# 待定\r\n效果分类：0x1\r\n";
+        assert_eq!(Xyyz::format_source(text), text.replace("0x1", "魔陷破坏"));
+    }
+
+    #[test]
+    fn test_include() {
+        let dir = std::env::temp_dir().join(format!("xyyz_include_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("part")).unwrap();
+        std::fs::write(dir.join("main.txt"), "甲(1) 通常魔法\n\n#include part/b.txt\n乙(2) 通常魔法\n").unwrap();
+        std::fs::write(dir.join("part/b.txt"), "#include ../main.txt\n丙(3) 通常魔法\n").unwrap();
+        let cards = Xyyz::open(dir.join("main.txt").to_str().unwrap());
+        assert_eq!(cards.iter().map(|c| c.code).collect::<Vec<_>>(), vec![1, 3, 2]);
+        assert!(cards[1].provenance.to_string().ends_with("part/b.txt:2:1"));
+        assert_eq!(cards[2].provenance.span.map(|s| (s.start_line, s.end_line, s.end_column)), Some((4, 4, 9)));
+        assert!(cards.iter().all(|c| c.comments.iter().all(|c| !c.starts_with("#include"))));
+        assert_eq!(Xyyz::merge_string(cards.iter()), "甲(1) 通常魔法\n\n丙(3) 通常魔法\n\n乙(2) 通常魔法");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_rush_text() {
+        let text = "超魔辉兽 大霸道王[L](160001000) 暗 10星 魔导骑士 0 2000 极大攻击力3500 (Rush)\n①：自己场上的怪兽攻击力上升500。\n\n传说之剑(160001001) 传说装备魔法 (Rush)\n①：装备怪兽攻击力上升1000。";
+        let cards = Xyyz::from_string(text);
+        assert!(cards[0]._type.contains(Type::Maximum));
+        assert_eq!(cards[0].race.bits(), Race::MagicalKnight.bits());
+        let unnamed = Race::MagicalKnight | Race::from_bits_retain(1 << 40);
+        assert_eq!(Xyyz::format_race(&unnamed), "魔导骑士/0x10000000000");
+        assert_eq!(Xyyz::get_race("魔导骑士/0x10000000000").bits(), unnamed.bits());
+        assert_eq!(cards[0].maximum_attack, 3500);
+        assert_eq!(cards[1]._type.bits(), (Type::Spell | Type::Equip | Type::Legend).bits());
+        assert_eq!(Xyyz::to_string(&cards[0]).lines().next(), text.lines().next());
+        let parsed = SQL::from_string(&SQL::to_string(&cards[0]));
+        assert_eq!((parsed[0].maximum_attack, parsed[0]._type.bits()), (3500, cards[0]._type.bits()));
+        assert!(SQL::to_string(&cards[0]).contains(&format!(",{},", (Type::Monster | Type::Effect).bits())));
+        for maximum in ["?", "∞", "0"] {
+            let cards = Xyyz::from_string(&text.replace("3500", maximum));
+            let parsed = SQL::from_string_with(&SQL::to_string_with(&cards[0], Dialect::EDOPro), Dialect::EDOPro);
+            assert!(parsed[0]._type.contains(Type::Maximum));
+            assert_eq!(Xyyz::to_string(&parsed[0]).lines().next(), text.replace("3500", maximum).lines().next());
+        }
+    }
+
+    #[test]
+    fn test_parse_original_header() {
+        let text = "INFO-JP000(QCSER)誇りと魂の龍(骄傲与灵魂之龙) 暗 8星 龙/特殊召唤 2500 2500\n这张卡不能通常召唤。";
+        let cards = Xyyz::from_string(text);
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].name, "骄傲与灵魂之龙");
+        assert_eq!(cards[0].code, 0);
+        assert_eq!(cards[0].attack, 2500);
+        let pack = cards[0].pack.as_ref().unwrap();
+        assert_eq!((pack.pack_id.as_str(), pack.rarity.clone(), pack.japanese_name.as_str()), ("INFO-JP000", vec!["QCSER".to_string()], "誇りと魂の龍"));
+        assert_eq!(Xyyz::to_string_with(&cards[0], HeaderStyle::Original), text);
+        assert_eq!(Xyyz::to_string(&cards[0]).lines().next(), Some("[INFO-JP000 QCSER] 骄傲与灵魂之龙 暗 8星 龙/特殊召唤 2500 2500"));
+        let text = "INFO-JP000(QCSER)誇りと魂の龍(骄傲与灵魂之龙)(100000000=>99) 暗 8星 龙/特殊召唤 2500 2500\n这张卡不能通常召唤。";
+        let cards = Xyyz::from_string(text);
+        assert_eq!((cards[0].code, cards[0].alias, cards[0].name.as_str()), (100000000, 99, "骄傲与灵魂之龙"));
+        assert_eq!(Xyyz::to_string_with(&cards[0], HeaderStyle::Original), text);
+        assert_eq!(Xyyz::from_string(&Xyyz::to_string(&cards[0]))[0].code, 100000000);
+    }
+
+    #[test]
+    fn test_parse_category() {
+        let text = "强欲之壶(55144522) 通常魔法\n从卡组抽2张。\n效果分类：抽卡辅助、0x300000000";
+        let cards = Xyyz::from_string(text);
+        assert_eq!(cards[0].category.bits(), Category::Draw.bits() | 0x300000000);
+        assert_eq!(Xyyz::to_string(&cards[0]), text);
+        assert_eq!(SQL::from_string(&SQL::to_string(&cards[0]))[0].category.bits(), cards[0].category.bits());
+    }
+
     #[test]
     fn test_format_and_parse_weak_text() {
         let file = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/transformers/test_data/xyyz_weak.txt");
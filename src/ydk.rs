@@ -0,0 +1,165 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::card::Card;
+use crate::constants::Type;
+
+/// Types that go to the extra deck.
+pub const EXTRA_TYPES: Type = Type::Fusion.union(Type::Synchro).union(Type::Xyz).union(Type::Link);
+
+/// The codes of a ydk deck, with the lines they are written on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deck {
+    pub main: Vec<(usize, u32)>,
+    pub extra: Vec<(usize, u32)>,
+    pub side: Vec<(usize, u32)>,
+    /// Lines of the `#main`, `#extra` and `!side` headers, 0 for a missing one.
+    pub section_lines: [usize; 3]
+}
+
+/// Something wrong with a deck, at a line of its ydk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub rule: &'static str,
+    pub line: usize,
+    pub message: String
+}
+
+impl Deck {
+    /// Reads the `#main`, `#extra` and `!side` sections. Other `#` lines are comments.
+    pub fn parse(text: &str) -> Deck {
+        let mut deck = Deck::default();
+        let mut section = &mut deck.main;
+        for (n, raw_line) in text.split("\n").enumerate() {
+            let line = raw_line.trim();
+            match line {
+                "#main" => { section = &mut deck.main; deck.section_lines[0] = n + 1 }
+                "#extra" => { section = &mut deck.extra; deck.section_lines[1] = n + 1 }
+                "!side" => { section = &mut deck.side; deck.section_lines[2] = n + 1 }
+                _ => if let Ok(code) = line.parse::<u32>() { section.push((n + 1, code)) }
+            }
+        }
+        deck
+    }
+
+    pub fn from_codes(codes: impl Iterator<Item = u32>) -> Deck {
+        Deck { main: codes.map(|code| (0, code)).collect(), ..Deck::default() }
+    }
+
+    pub fn codes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.main.iter().chain(self.extra.iter()).chain(self.side.iter()).map(|(_, code)| *code)
+    }
+
+    /// Checks codes against `cards`, where they are placed, how many there are and the limits of the cards.
+    pub fn check(&self, cards: &[Card]) -> Vec<Problem> {
+        let by_code = cards.iter().rev().map(|c| (c.code, c)).collect::<HashMap<_, _>>();
+        let mut problems = Vec::new();
+        for (i, (name, section, range)) in [("Main", &self.main, 40..=60), ("Extra", &self.extra, 0..=15), ("Side", &self.side, 0..=15)].into_iter().enumerate() {
+            if !range.contains(&section.len()) {
+                let line = section.first().map_or(self.section_lines[i], |(line, _)| *line);
+                problems.push(Problem { rule: "deck-size", line, message: format!("{} deck has {} cards, not {} to {}.", name, section.len(), range.start(), range.end()) });
+            }
+        }
+        // Side deck cards may go to either deck.
+        let placed = self.main.iter().map(|e| (e, Some(false)))
+            .chain(self.extra.iter().map(|e| (e, Some(true))))
+            .chain(self.side.iter().map(|e| (e, None)));
+        for ((line, code), in_extra) in placed {
+            let Some(card) = by_code.get(code) else {
+                problems.push(Problem { rule: "unknown-code", line: *line, message: format!("Card {} is not loaded.", code) });
+                continue;
+            };
+            let extra = card._type.contains(Type::Monster) && card._type.intersects(EXTRA_TYPES);
+            match in_extra {
+                Some(false) if extra => problems.push(Problem { rule: "wrong-deck", line: *line, message: format!("{}({}) belongs to the extra deck.", card.name, code) }),
+                Some(true) if !extra => problems.push(Problem { rule: "wrong-deck", line: *line, message: format!("{}({}) belongs to the main deck.", card.name, code) }),
+                _ => {}
+            }
+        }
+        // Cards with an alias count as copies of the original.
+        let mut copies = HashMap::<u32, Vec<(usize, &Card)>>::new();
+        for (line, code) in self.main.iter().chain(self.extra.iter()).chain(self.side.iter()) {
+            let Some(card) = by_code.get(code) else { continue };
+            copies.entry(if card.alias > 0 { card.alias } else { card.code }).or_default().push((*line, card));
+        }
+        let mut groups = copies.into_values().collect::<Vec<_>>();
+        groups.sort_by_key(|group| group[0].0);
+        for group in groups {
+            let (line, card) = group[0];
+            if group.len() > 3 {
+                problems.push(Problem { rule: "banlist", line, message: format!("{}({}) has {} copies, more than 3.", card.name, card.code, group.len()) });
+            }
+            let mut seen = HashSet::new();
+            for limit in group.iter().flat_map(|(_, c)| c.limits.iter()).filter(|l| seen.insert(&l.list)) {
+                let count = group.iter().flat_map(|(_, c)| c.limits.iter()).filter(|l| l.list == limit.list).map(|l| l.count).min().unwrap_or(limit.count);
+                if group.len() > count as usize {
+                    problems.push(Problem { rule: "banlist", line, message: format!("{}({}) has {} copies, {} allows {}.", card.name, card.code, group.len(), limit.list, count) });
+                }
+            }
+        }
+        problems
+    }
+
+    /// One of each card of the deck, in deck order.
+    pub fn cards(&self, cards: &[Card]) -> Vec<Card> {
+        let mut seen = HashSet::new();
+        self.codes()
+            .filter(|code| seen.insert(*code))
+            .filter_map(|code| cards.iter().find(|c| c.code == code).cloned())
+            .collect()
+    }
+}
+
+/// `text` with the name of each card as a comment line before its code, replacing the names written before.
+///
+/// The names follow a space, so that a name starting with `extra` doesn't pass for the `#extra` header.
+pub fn annotate(text: &str, cards: &[Card]) -> String {
+    let by_code = cards.iter().rev().map(|c| (c.code, c)).collect::<HashMap<_, _>>();
+    let name = |line: &str| line.trim().parse::<u32>().ok().and_then(|code| by_code.get(&code)).map(|c| c.name.as_str());
+    let lines = text.split("\n").collect::<Vec<_>>();
+    let mut annotated = Vec::new();
+    for (i, raw_line) in lines.iter().enumerate() {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        let ending = &raw_line[line.len()..];
+        if line.strip_prefix("# ").is_some_and(|comment| lines.get(i + 1).and_then(|next| name(next)) == Some(comment)) { continue }
+        if let Some(name) = name(line) { annotated.push(format!("# {}{}", name, ending)) }
+        annotated.push(raw_line.to_string());
+    }
+    annotated.join("\n")
+}
+
+/// A ydk of `deck` with `title` as a comment, as ygopro clients write it.
+pub fn write(title: &str, deck: &Deck) -> String {
+    let mut ydk = format!("#created by cdb-transformer\n#{}\n#main\n", title);
+    let lines = |codes: &Vec<(usize, u32)>| codes.iter().map(|(_, code)| format!("{}\n", code)).collect::<String>();
+    ydk += &lines(&deck.main);
+    ydk += "#extra\n";
+    ydk += &lines(&deck.extra);
+    ydk += "!side\n";
+    ydk + &lines(&deck.side)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::card::CardTransformer;
+    use crate::transformers::Xyyz;
+    use crate::ydk::*;
+
+    #[test]
+    fn test_check() {
+        let cards = Xyyz::from_string("甲(100) 光 4星 龙/效果 1000 1000\n禁限：自制 限制\n\n乙(101=>100) 光 4星 龙/效果 1000 1000\n\n丙(102) 光 8星 龙/融合 3000 2500\n\n丁(103) 通常魔法");
+        let deck = Deck::parse("#created by cdb-transformer\n#main\n100\n101\n102\n#extra\n103\n999\n!side\n101\n");
+        assert_eq!(deck.codes().collect::<Vec<_>>(), vec![100, 101, 102, 103, 999, 101]);
+        let problems = deck.check(&cards);
+        assert_eq!(problems.iter().map(|p| (p.rule, p.line)).collect::<Vec<_>>(), vec![
+            ("deck-size", 3), ("wrong-deck", 5), ("wrong-deck", 7), ("unknown-code", 8), ("banlist", 3)
+        ]);
+        assert_eq!(problems[4].message, "甲(100) has 3 copies, 自制 allows 1.");
+        assert_eq!(deck.cards(&cards).iter().map(|c| c.code).collect::<Vec<_>>(), vec![100, 101, 102, 103]);
+        assert_eq!(write("test", &Deck::from_codes([100, 101].into_iter())), "#created by cdb-transformer\n#test\n#main\n100\n101\n#extra\n!side\n");
+        assert_eq!(Deck::parse("#created by cdb-transformer\n#main\n#extra\n103\n!side\n").check(&cards)[0].line, 2);
+        let annotated = annotate("#main\r\n100\r\n999\r\n#extra\r\n103\r\n", &cards);
+        assert_eq!(annotated, "#main\r\n# 甲\r\n100\r\n999\r\n#extra\r\n# 丁\r\n103\r\n");
+        assert_eq!(annotate(&annotated, &cards), annotated);
+        assert_eq!(Deck::parse(&annotated).codes().collect::<Vec<_>>(), vec![100, 999, 103]);
+    }
+}